@@ -0,0 +1,34 @@
+# Alacritty Chat のシェル統合（bash）
+#
+# ~/.bashrc の末尾で読み込む:
+#   source /path/to/shell-integration/alacritty-chat.bash
+#
+# プロンプトとコマンドの区切りを FinalTerm 形式（OSC 133）のマーカーで端末に知らせる。
+
+[[ "$TERM_PROGRAM" == "alacritty_chat" ]] || return 0
+[[ -n "$__alacritty_chat_loaded" ]] && return 0
+__alacritty_chat_loaded=1
+
+# 直前のコマンドの終了コードを知らせる（D）
+__alacritty_chat_status() {
+    local status=$?
+    printf '\e]133;D;%s\a' "$status"
+    return $status
+}
+
+# プロンプトの前後にマーカーを付ける（A と B）
+#
+# PROMPT_COMMAND で PS1 を作り直す設定もあるため、毎回確認して付け直す。
+__alacritty_chat_prompt() {
+    local status=$?
+    if [[ "$PS1" != *'133;B'* ]]; then
+        PS1='\[\e]133;A\a\]'"$PS1"'\[\e]133;B\a\]'
+    fi
+    return $status
+}
+
+# コマンドの実行開始を知らせる（C）
+PS0+='\e]133;C\a'
+
+# 終了コードは他の処理より先に、プロンプトの加工は最後に行う
+PROMPT_COMMAND="__alacritty_chat_status${PROMPT_COMMAND:+; $PROMPT_COMMAND}; __alacritty_chat_prompt"
@@ -0,0 +1,35 @@
+# Alacritty Chat のシェル統合（fish）
+#
+# ~/.config/fish/config.fish の末尾で読み込む:
+#   source /path/to/shell-integration/alacritty-chat.fish
+#
+# プロンプトとコマンドの区切りを FinalTerm 形式（OSC 133）のマーカーで端末に知らせる。
+
+if test "$TERM_PROGRAM" = alacritty_chat; and not set -q __alacritty_chat_loaded
+    set -g __alacritty_chat_loaded 1
+
+    # 元のプロンプトが参照できるように終了コードを戻す
+    function __alacritty_chat_restore_status
+        return $argv[1]
+    end
+
+    # プロンプトの前後にマーカーを付ける（A と B）
+    functions -c fish_prompt __alacritty_chat_original_prompt
+    function fish_prompt
+        set -l last_status $status
+        printf '\e]133;A\a'
+        __alacritty_chat_restore_status $last_status
+        __alacritty_chat_original_prompt
+        printf '\e]133;B\a'
+    end
+
+    # コマンドの実行開始を知らせる（C）
+    function __alacritty_chat_preexec --on-event fish_preexec
+        printf '\e]133;C\a'
+    end
+
+    # コマンドの終了コードを知らせる（D）
+    function __alacritty_chat_postexec --on-event fish_postexec
+        printf '\e]133;D;%s\a' $status
+    end
+end
@@ -1,32 +1,147 @@
-use egui::{CentralPanel, SidePanel};
+use egui::{CentralPanel, SidePanel, TopBottomPanel};
 use eframe::App;
 
-use crate::terminal::TerminalPane;
+use crate::config::TerminalConfig;
+use crate::settings::SettingsWindow;
+use crate::tabs::TerminalTabs;
+use crate::terminal::{font, LinkAction, TerminalPane};
 use crate::chat::ChatPanel;
 
-pub struct AppState {
+// アプリケーション名（ウィンドウタイトルの既定値）
+pub const APP_NAME: &str = "Alacritty Chat";
+
+// リンクから開いたエディタのウィンドウ
+struct EditorWindow {
+    id: egui::Id,
+    title: String,
     terminal: TerminalPane,
+}
+
+pub struct AppState {
+    tabs: TerminalTabs,
+    editors: Vec<EditorWindow>,
+    next_editor_id: u64,
     chat: ChatPanel,
+    config: TerminalConfig,
+    settings: SettingsWindow,
+    config_unsaved: bool, // 変更した設定をまだ保存していない
+    config_writable: bool, // 読み込めなかった設定ファイルは上書きしない
+    window_title: String, // 現在のウィンドウタイトル
+    layout_message: Option<String>, // ペインの配置の保存・復元の結果
 }
 
 impl AppState {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        // カスタムフォントを設定したい場合はここで行う
         let ctx = &cc.egui_ctx;
         
-        // ターミナルとチャットパネルの初期化
-        let terminal = TerminalPane::new();
+        // ターミナル用のフォントを登録
+        let (config, load_error) = match TerminalConfig::load() {
+            Ok(config) => (config, None),
+            Err(e) => {
+                eprintln!("設定の読み込みに失敗しました: {}", e);
+                (TerminalConfig::default(), Some(e))
+            },
+        };
+        if let Err(e) = font::install(ctx, &config.font) {
+            eprintln!("フォントの読み込みに失敗しました: {}", e);
+        }
+        
+        // ターミナルのタブとチャットパネルの初期化
+        let tabs = TerminalTabs::new(ctx, &config);
         let chat = ChatPanel::new();
+        let mut settings = SettingsWindow::new(&config);
+        
+        // 壊れた設定ファイルを既定値で上書きしないよう、読み込めなかった場合は保存しない
+        let config_writable = load_error.is_none();
+        settings.file_error = load_error.map(|e| {
+            format!("設定ファイルを読み込めなかったため、変更は保存されません: {}", e)
+        });
         
         Self {
-            terminal,
+            tabs,
+            editors: Vec::new(),
+            next_editor_id: 0,
             chat,
+            config,
+            settings,
+            config_unsaved: false,
+            config_writable,
+            window_title: APP_NAME.to_string(),
+            layout_message: None,
+        }
+    }
+    
+    // 変更した設定を次回の起動のために保存する
+    fn save_config(&mut self) {
+        if !self.config_unsaved || !self.config_writable {
+            return;
+        }
+        self.config_unsaved = false;
+        self.settings.file_error = self
+            .config
+            .save()
+            .err()
+            .map(|e| format!("設定の保存に失敗しました: {}", e));
+    }
+    
+    // ターミナルで操作されたリンクを処理する
+    fn handle_link_action(&mut self, ctx: &egui::Context, action: LinkAction) {
+        match action {
+            LinkAction::OpenInEditor(location) => {
+                let terminal = TerminalPane::with_command(ctx, self.config.clone(), location.editor_command());
+                self.editors.push(EditorWindow {
+                    id: egui::Id::new(("editor", self.next_editor_id)),
+                    title: location.to_string(),
+                    terminal,
+                });
+                self.next_editor_id += 1;
+            },
+            LinkAction::SendToChat(text) => self.chat.add_context(&text),
         }
     }
 }
 
 impl App for AppState {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // メニューバー
+        TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("⚙ 設定").clicked() {
+                    self.settings.open = !self.settings.open;
+                }
+                
+                ui.separator();
+                if ui.button("レイアウトを保存").on_hover_text("選択中のタブのペインの配置を保存").clicked() {
+                    self.layout_message = Some(match self.tabs.save_layout() {
+                        Ok(path) => format!("保存しました: {}", path.display()),
+                        Err(e) => format!("保存に失敗しました: {}", e),
+                    });
+                }
+                if ui.button("レイアウトを復元").on_hover_text("保存したペインの配置を新しいタブで開く").clicked() {
+                    self.layout_message = match self.tabs.restore_layout(ctx, &self.config) {
+                        Ok(()) => None,
+                        Err(e) => Some(format!("復元に失敗しました: {}", e)),
+                    };
+                }
+                if let Some(message) = &self.layout_message {
+                    ui.weak(message);
+                }
+            });
+        });
+        
+        // 設定が変更されたらターミナルに反映する。
+        // スライダーのドラッグ中などは保存せず、操作を終えてから保存する
+        if self.settings.show(ctx, &mut self.config) {
+            self.config_unsaved = true;
+            self.tabs.set_config(&self.config);
+            for editor in &mut self.editors {
+                editor.terminal.set_config(self.config.clone());
+            }
+        }
+        if !self.settings.editing() {
+            self.save_config();
+        }
+        
         // 左側にチャットパネル、右側にターミナルを配置
         SidePanel::left("chat_panel")
             .resizable(true)
@@ -36,7 +151,41 @@ impl App for AppState {
             });
             
         CentralPanel::default().show(ctx, |ui| {
-            self.terminal.ui(ui);
+            self.tabs.ui(ui, &self.config);
+        });
+        
+        // エディタのウィンドウ（エディタが終了したら閉じる）
+        self.editors.retain_mut(|editor| {
+            let mut open = true;
+            egui::Window::new(&editor.title)
+                .id(editor.id)
+                .open(&mut open)
+                .default_size([700.0, 450.0])
+                .show(ctx, |ui| editor.terminal.ui(ui));
+            open && !editor.terminal.has_exited()
         });
+        
+        // ターミナルで操作されたリンクを処理
+        let mut actions = self.tabs.take_link_actions();
+        for editor in &mut self.editors {
+            actions.extend(editor.terminal.take_link_actions());
+        }
+        for action in actions {
+            self.handle_link_action(ctx, action);
+        }
+        
+        // 選択中のタブのタイトルをウィンドウタイトルに反映
+        let title = match self.tabs.title() {
+            Some(title) => format!("{} - {}", title, APP_NAME),
+            None => APP_NAME.to_string(),
+        };
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+    }
+    
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_config();
     }
-} 
\ No newline at end of file
+} 
@@ -1,9 +1,11 @@
-use egui::{ScrollArea, TextEdit, Button, RichText, TextStyle, Color32, Layout, Align};
+use egui::{ScrollArea, TextEdit, Button, RichText, Color32, Layout, Align};
 use serde::{Serialize, Deserialize};
 use std::sync::mpsc::{self, Receiver};
 use std::thread;
 use std::time::Duration;
 
+// 現在は役割を文字列で持っているため未使用
+#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum ChatRole {
     User,
@@ -154,6 +156,8 @@ pub struct ChatPanel {
     input_buffer: String,
     awaiting_response: bool,
     rx: Option<Receiver<Result<String, String>>>,
+    // ワーカースレッドと共有できないため、送信には現在モックを使っている
+    #[allow(dead_code)]
     llm_service: Box<dyn LLMService>,
 }
 
@@ -281,6 +285,15 @@ impl ChatPanel {
         });
     }
     
+    // ターミナルから受け取ったテキストを入力欄に追加する
+    pub fn add_context(&mut self, text: &str) {
+        if !self.input_buffer.is_empty() && !self.input_buffer.ends_with('\n') {
+            self.input_buffer.push('\n');
+        }
+        self.input_buffer.push_str(text);
+        self.input_buffer.push('\n');
+    }
+    
     fn send_message(&mut self) {
         if self.input_buffer.trim().is_empty() || self.awaiting_response {
             return;
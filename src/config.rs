@@ -0,0 +1,160 @@
+use std::{io, path::PathBuf};
+use serde::{Deserialize, Serialize};
+
+// 設定ファイルの名前
+const CONFIG_FILE: &str = "config.json";
+
+// ターミナルの設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalConfig {
+    // フォントの設定
+    pub font: FontConfig,
+    // 太字のテキストを明るい色（8～15番）で描画する
+    pub draw_bold_text_with_bright_colors: bool,
+    // スクロールバックに保持する最大行数
+    pub scrollback_lines: usize,
+    // 複数行や制御文字を含むテキストを貼り付ける前に確認する
+    pub confirm_unsafe_paste: bool,
+    // カーソルの設定
+    pub cursor: CursorConfig,
+    // OSC 52 によるクリップボードへの書き込みの扱い
+    pub osc52_copy: ClipboardPolicy,
+    // OSC 52 によるクリップボードの読み取りの扱い
+    pub osc52_paste: ClipboardPolicy,
+    // ヒントモードでURLやパスなどに加えて検出する正規表現
+    pub hint_patterns: Vec<String>,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            font: FontConfig::default(),
+            draw_bold_text_with_bright_colors: false,
+            scrollback_lines: 10_000,
+            confirm_unsafe_paste: true,
+            cursor: CursorConfig::default(),
+            osc52_copy: ClipboardPolicy::Allow,
+            osc52_paste: ClipboardPolicy::Deny,
+            hint_patterns: Vec::new(),
+        }
+    }
+}
+
+impl TerminalConfig {
+    // 設定ファイルから読み込む（ファイルがなければ既定値を使う）
+    //
+    // ファイルにない項目も既定値で補う。
+    pub fn load() -> io::Result<Self> {
+        match std::fs::read_to_string(config_dir()?.join(CONFIG_FILE)) {
+            Ok(json) => serde_json::from_str(&json).map_err(io::Error::other),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+    
+    // 設定ファイルに保存する
+    pub fn save(&self) -> io::Result<()> {
+        let dir = config_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(dir.join(CONFIG_FILE), json)
+    }
+}
+
+// 設定ファイルを置くディレクトリ（$XDG_CONFIG_HOME/alacritty_chat）
+pub fn config_dir() -> io::Result<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "設定ディレクトリが見つかりません"))?;
+    Ok(config_home.join("alacritty_chat"))
+}
+
+// ターミナルのフォント設定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FontConfig {
+    // フォントファイル（TTF/OTF）のパス。None の場合は組み込みの等幅フォントを使う
+    pub path: Option<PathBuf>,
+    // フォントサイズ（ポイント）
+    pub size: f32,
+    // 行の高さの倍率
+    pub line_spacing: f32,
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            size: 13.0,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+// カーソルの設定
+//
+// アプリケーションがDECSCUSRで形状を指定していない場合に使われる。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CursorConfig {
+    // カーソルの形状
+    pub shape: CursorShape,
+    // 点滅させる
+    pub blinking: bool,
+    // 点滅の間隔（ミリ秒）
+    pub blink_interval_ms: u64,
+}
+
+impl Default for CursorConfig {
+    fn default() -> Self {
+        Self {
+            shape: CursorShape::Block,
+            blinking: false,
+            blink_interval_ms: 750,
+        }
+    }
+}
+
+// カーソルの形状
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CursorShape {
+    Block,
+    Underline,
+    Beam,
+}
+
+impl CursorShape {
+    pub const ALL: [CursorShape; 3] = [CursorShape::Block, CursorShape::Underline, CursorShape::Beam];
+    
+    // 設定画面での表示名
+    pub fn label(self) -> &'static str {
+        match self {
+            CursorShape::Block => "ブロック",
+            CursorShape::Underline => "下線",
+            CursorShape::Beam => "縦線",
+        }
+    }
+}
+
+// 端末内のプログラムからのクリップボード操作を許可するかどうか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClipboardPolicy {
+    Allow,
+    Deny,
+    Ask,
+}
+
+impl ClipboardPolicy {
+    pub const ALL: [ClipboardPolicy; 3] = [ClipboardPolicy::Allow, ClipboardPolicy::Deny, ClipboardPolicy::Ask];
+    
+    // 設定画面での表示名
+    pub fn label(self) -> &'static str {
+        match self {
+            ClipboardPolicy::Allow => "許可",
+            ClipboardPolicy::Deny => "拒否",
+            ClipboardPolicy::Ask => "確認する",
+        }
+    }
+}
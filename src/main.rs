@@ -1,4 +1,8 @@
 mod app;
+mod config;
+mod panes;
+mod settings;
+mod tabs;
 mod terminal;
 mod chat;
 
@@ -26,7 +30,7 @@ fn main() {
     };
     
     match eframe::run_native(
-        "Alacritty Chat",
+        app::APP_NAME,
         options,
         Box::new(|cc| Ok(Box::new(app::AppState::new(cc))))
     ) {
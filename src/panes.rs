@@ -0,0 +1,512 @@
+use std::io;
+use std::path::PathBuf;
+use egui::{Color32, CursorIcon, Key, Modifiers, Rect, Sense, Stroke, UiBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, TerminalConfig};
+use crate::terminal::{LinkAction, ShellCommand, TerminalPane};
+
+// ペインの間の仕切りの幅（ピクセル）
+const DIVIDER_WIDTH: f32 = 4.0;
+
+// 仕切りをドラッグして狭められる最小の割合
+const MIN_RATIO: f32 = 0.1;
+
+// ペインの分割方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplitDirection {
+    // 左右に並べる
+    Horizontal,
+    // 上下に並べる
+    Vertical,
+}
+
+// 保存・復元するペインの配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Layout {
+    Pane {
+        working_directory: Option<PathBuf>,
+    },
+    Split {
+        direction: SplitDirection,
+        ratio: f32, // 1つ目のペインが占める割合
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+// ペインの配置の木（葉はペインのID）
+enum Node {
+    Pane(egui::Id),
+    Split {
+        direction: SplitDirection,
+        ratio: f32,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    // 指定したペインを分割し、新しいペインを2つ目に置く
+    fn split(&mut self, target: egui::Id, new: egui::Id, direction: SplitDirection) -> bool {
+        match self {
+            Node::Pane(id) if *id == target => {
+                *self = Node::Split {
+                    direction,
+                    ratio: 0.5,
+                    first: Box::new(Node::Pane(target)),
+                    second: Box::new(Node::Pane(new)),
+                };
+                true
+            },
+            Node::Pane(_) => false,
+            Node::Split { first, second, .. } => {
+                first.split(target, new, direction) || second.split(target, new, direction)
+            },
+        }
+    }
+    
+    // 指定したペインを取り除き、残ったペインに領域を譲る
+    fn remove(&mut self, target: egui::Id) -> bool {
+        let Node::Split { first, second, .. } = self else {
+            return false;
+        };
+        
+        let sibling = if matches!(**first, Node::Pane(id) if id == target) {
+            std::mem::replace(&mut **second, Node::Pane(target))
+        } else if matches!(**second, Node::Pane(id) if id == target) {
+            std::mem::replace(&mut **first, Node::Pane(target))
+        } else {
+            return first.remove(target) || second.remove(target);
+        };
+        *self = sibling;
+        true
+    }
+    
+    // 各ペインの領域を求め、仕切りのドラッグで割合を変える
+    fn layout_ui(&mut self, ui: &egui::Ui, rect: Rect, id: egui::Id, rects: &mut Vec<(egui::Id, Rect)>) {
+        let (direction, ratio, first, second) = match self {
+            Node::Pane(pane) => {
+                rects.push((*pane, rect));
+                return;
+            },
+            Node::Split { direction, ratio, first, second } => (*direction, ratio, first, second),
+        };
+        
+        let (first_rect, divider, second_rect) = split_rect(rect, direction, *ratio);
+        
+        let response = ui.interact(divider, id.with("divider"), Sense::drag());
+        if response.hovered() || response.dragged() {
+            ui.ctx().set_cursor_icon(match direction {
+                SplitDirection::Horizontal => CursorIcon::ResizeHorizontal,
+                SplitDirection::Vertical => CursorIcon::ResizeVertical,
+            });
+        }
+        if let Some(pos) = response.interact_pointer_pos().filter(|_| response.dragged()) {
+            let position = match direction {
+                SplitDirection::Horizontal => (pos.x - rect.left()) / rect.width(),
+                SplitDirection::Vertical => (pos.y - rect.top()) / rect.height(),
+            };
+            *ratio = position.clamp(MIN_RATIO, 1.0 - MIN_RATIO);
+        }
+        
+        let color = if response.hovered() || response.dragged() {
+            ui.visuals().widgets.hovered.bg_stroke.color
+        } else {
+            ui.visuals().widgets.noninteractive.bg_stroke.color
+        };
+        ui.painter().rect_filled(divider, 0.0, color);
+        
+        first.layout_ui(ui, first_rect, id.with(0), rects);
+        second.layout_ui(ui, second_rect, id.with(1), rects);
+    }
+    
+    // 保存用の配置に変換する
+    fn to_layout(&self, panes: &[Pane]) -> Layout {
+        match self {
+            Node::Pane(id) => Layout::Pane {
+                working_directory: panes
+                    .iter()
+                    .find(|pane| pane.id == *id)
+                    .and_then(|pane| pane.terminal.working_directory()),
+            },
+            Node::Split { direction, ratio, first, second } => Layout::Split {
+                direction: *direction,
+                ratio: *ratio,
+                first: Box::new(first.to_layout(panes)),
+                second: Box::new(second.to_layout(panes)),
+            },
+        }
+    }
+}
+
+// ターミナルを表示するペイン
+struct Pane {
+    id: egui::Id,
+    terminal: TerminalPane,
+}
+
+// 1つのタブの中を分割した複数のターミナル
+//
+// Ctrl+Shift+E で左右に、Ctrl+Shift+D で上下に分割し、Alt+矢印キーで
+// フォーカスを移す。Ctrl+Shift+Z で選択中のペインだけを表示する。
+pub struct PaneTree {
+    id: egui::Id,
+    root: Node,
+    panes: Vec<Pane>,
+    active: egui::Id, // キーボード入力を受け取るペイン
+    zoomed: bool, // 選択中のペインだけを表示する
+    next_id: u64,
+    pane_rects: Vec<(egui::Id, Rect)>, // 前回のフレームで各ペインを表示した領域
+}
+
+impl PaneTree {
+    // シェルを1つ起動したペインで作成する
+    pub fn new(ctx: &egui::Context, config: &TerminalConfig, id: egui::Id) -> Self {
+        Self::from_layout(ctx, config, id, &Layout::Pane { working_directory: None })
+    }
+    
+    // 保存した配置を復元する（各ペインは保存時の作業ディレクトリでシェルを起動する）
+    pub fn from_layout(ctx: &egui::Context, config: &TerminalConfig, id: egui::Id, layout: &Layout) -> Self {
+        let mut tree = Self {
+            id,
+            root: Node::Pane(id),
+            panes: Vec::new(),
+            active: id,
+            zoomed: false,
+            next_id: 0,
+            pane_rects: Vec::new(),
+        };
+        tree.root = tree.build(ctx, config, layout);
+        tree.active = tree.panes[0].id;
+        tree.panes[0].terminal.request_focus();
+        tree
+    }
+    
+    fn build(&mut self, ctx: &egui::Context, config: &TerminalConfig, layout: &Layout) -> Node {
+        match layout {
+            Layout::Pane { working_directory } => {
+                Node::Pane(self.add_pane(ctx, config, working_directory.clone()))
+            },
+            Layout::Split { direction, ratio, first, second } => Node::Split {
+                direction: *direction,
+                ratio: ratio.clamp(MIN_RATIO, 1.0 - MIN_RATIO),
+                first: Box::new(self.build(ctx, config, first)),
+                second: Box::new(self.build(ctx, config, second)),
+            },
+        }
+    }
+    
+    fn add_pane(&mut self, ctx: &egui::Context, config: &TerminalConfig, working_directory: Option<PathBuf>) -> egui::Id {
+        // 保存後に削除されたディレクトリでは起動できないため、その場合は既定の場所で起動する
+        let working_directory = working_directory.filter(|dir| dir.is_dir());
+        let command = ShellCommand { working_directory, ..ShellCommand::shell() };
+        let id = self.id.with(("pane", self.next_id));
+        self.next_id += 1;
+        self.panes.push(Pane { id, terminal: TerminalPane::with_command(ctx, config.clone(), command) });
+        id
+    }
+    
+    // 保存用の配置
+    pub fn layout(&self) -> Layout {
+        self.root.to_layout(&self.panes)
+    }
+    
+    fn active_pane(&self) -> &Pane {
+        self.panes.iter().find(|pane| pane.id == self.active).unwrap_or(&self.panes[0])
+    }
+    
+    // 選択中のペインを選び直し、ターミナルにフォーカスを移す
+    fn select(&mut self, id: egui::Id) {
+        if let Some(pane) = self.panes.iter_mut().find(|pane| pane.id == id) {
+            pane.terminal.request_focus();
+            self.active = id;
+        }
+    }
+    
+    // 次に表示したときに選択中のペインにフォーカスを移す
+    pub fn request_focus(&mut self) {
+        self.select(self.active);
+    }
+    
+    // 選択中のペインを分割する（新しいペインは同じ作業ディレクトリで起動する）
+    fn split(&mut self, ctx: &egui::Context, config: &TerminalConfig, direction: SplitDirection) {
+        let working_directory = self.active_pane().terminal.working_directory();
+        let new = self.add_pane(ctx, config, working_directory);
+        self.root.split(self.active, new, direction);
+        self.zoomed = false;
+        self.select(new);
+    }
+    
+    // ペインの数
+    pub fn pane_count(&self) -> usize {
+        self.panes.len()
+    }
+    
+    // 選択中のペインを閉じる（最後のペインは閉じない）
+    pub fn close_active(&mut self) {
+        if self.panes.len() <= 1 || !self.root.remove(self.active) {
+            return;
+        }
+        
+        let index = self.panes.iter().position(|pane| pane.id == self.active).unwrap_or(0);
+        self.panes.remove(index);
+        self.zoomed = false;
+        
+        // 閉じたペインの次（なければ前）のペインを選ぶ
+        let next = self.panes[index.min(self.panes.len() - 1)].id;
+        self.select(next);
+    }
+    
+    // 指定した向きで隣にあるペイン
+    //
+    // 向きの先にあり、直交する方向に重なるペインのうち最も近いものを返す。
+    fn neighbour(&self, key: Key) -> Option<egui::Id> {
+        let (_, from) = self.pane_rects.iter().find(|(id, _)| *id == self.active)?;
+        self.pane_rects
+            .iter()
+            .filter(|(id, rect)| {
+                *id != self.active
+                    && match key {
+                        Key::ArrowLeft => rect.right() <= from.left() && overlaps(rect.y_range(), from.y_range()),
+                        Key::ArrowRight => rect.left() >= from.right() && overlaps(rect.y_range(), from.y_range()),
+                        Key::ArrowUp => rect.bottom() <= from.top() && overlaps(rect.x_range(), from.x_range()),
+                        Key::ArrowDown => rect.top() >= from.bottom() && overlaps(rect.x_range(), from.x_range()),
+                        _ => false,
+                    }
+            })
+            .min_by(|(_, a), (_, b)| {
+                let distance = |rect: &Rect| rect.center().distance(from.center());
+                distance(a).total_cmp(&distance(b))
+            })
+            .map(|(id, _)| *id)
+    }
+    
+    // ペインを分割して表示する
+    pub fn ui(&mut self, ui: &mut egui::Ui, config: &TerminalConfig) {
+        // ショートカットはペインにフォーカスがあるときだけ使う
+        if self.has_focus(ui.ctx()) {
+            self.handle_shortcuts(ui, config);
+        }
+        
+        let rect = ui.available_rect_before_wrap();
+        ui.allocate_rect(rect, Sense::hover());
+        
+        let mut rects = Vec::new();
+        if self.zoomed {
+            rects.push((self.active, rect));
+        } else {
+            self.root.layout_ui(ui, rect, self.id.with("layout"), &mut rects);
+        }
+        
+        // クリックしたペインを選択中にする
+        let pressed = ui.input(|i| i.pointer.primary_pressed().then(|| i.pointer.interact_pos()).flatten());
+        if let Some(pos) = pressed {
+            if let Some((id, _)) = rects.iter().find(|(_, rect)| rect.contains(pos)) {
+                self.active = *id;
+            }
+        }
+        
+        for pane in &mut self.panes {
+            match rects.iter().find(|(id, _)| *id == pane.id) {
+                Some((_, rect)) => {
+                    let mut child = ui.new_child(UiBuilder::new().max_rect(*rect).id_salt(pane.id));
+                    pane.terminal.ui(&mut child);
+                },
+                // 表示していないペインも端末からのイベントを処理する
+                None => pane.terminal.process_events(ui.ctx()),
+            }
+        }
+        
+        // 複数のペインがある場合は選択中のペインを枠で示す
+        if rects.len() > 1 {
+            if let Some((_, rect)) = rects.iter().find(|(id, _)| *id == self.active) {
+                let color = ui.visuals().selection.stroke.color;
+                ui.painter().rect_stroke(*rect, 0.0, Stroke::new(1.0, color), egui::StrokeKind::Inside);
+            }
+        } else if self.zoomed && self.panes.len() > 1 {
+            // 拡大表示中であることを示す
+            let color = Color32::from_rgba_unmultiplied(255, 255, 255, 40);
+            ui.painter().rect_stroke(rect, 0.0, Stroke::new(2.0, color), egui::StrokeKind::Inside);
+        }
+        
+        self.pane_rects = rects;
+    }
+    
+    // ペインを操作するキーボードショートカット
+    fn handle_shortcuts(&mut self, ui: &egui::Ui, config: &TerminalConfig) {
+        let ctrl_shift = Modifiers::CTRL | Modifiers::SHIFT;
+        let consume = |modifiers, key| ui.input_mut(|i| i.consume_key(modifiers, key));
+        
+        if consume(ctrl_shift, Key::E) {
+            self.split(ui.ctx(), config, SplitDirection::Horizontal);
+        }
+        if consume(ctrl_shift, Key::D) {
+            self.split(ui.ctx(), config, SplitDirection::Vertical);
+        }
+        if consume(ctrl_shift, Key::Z) && self.panes.len() > 1 {
+            self.zoomed = !self.zoomed;
+            self.request_focus();
+        }
+        
+        // Alt+矢印キーは隣にペインがあるときだけ使い、それ以外はシェルに送る
+        // （xtermの修飾付きカーソルキーとしてエディタなどが使う）
+        let alt_only = ui.input(|i| i.modifiers.matches_exact(Modifiers::ALT));
+        if !self.zoomed && alt_only {
+            for key in [Key::ArrowLeft, Key::ArrowRight, Key::ArrowUp, Key::ArrowDown] {
+                if let Some(id) = self.neighbour(key) {
+                    if consume(Modifiers::ALT, key) {
+                        self.select(id);
+                    }
+                }
+            }
+        }
+    }
+    
+    // いずれかのペインがキーボードフォーカスを持っているかどうか
+    pub fn has_focus(&self, ctx: &egui::Context) -> bool {
+        self.panes.iter().any(|pane| pane.terminal.has_focus(ctx))
+    }
+    
+    // 選択中のペインのターミナル
+    pub fn active_terminal(&self) -> &TerminalPane {
+        &self.active_pane().terminal
+    }
+    
+    // いずれかのペインでフォアグラウンドで実行中のコマンド
+    pub fn foreground_job(&self) -> Option<String> {
+        self.panes.iter().find_map(|pane| pane.terminal.foreground_job())
+    }
+    
+    // 端末からのイベントを処理する（タブを表示していないとき用）
+    pub fn process_events(&mut self, ctx: &egui::Context) {
+        for pane in &mut self.panes {
+            pane.terminal.process_events(ctx);
+        }
+    }
+    
+    // 設定をすべてのペインに反映する
+    pub fn set_config(&mut self, config: &TerminalConfig) {
+        for pane in &mut self.panes {
+            pane.terminal.set_config(config.clone());
+        }
+    }
+    
+    // すべてのペインで操作されたリンクを取り出す
+    pub fn take_link_actions(&mut self) -> Vec<LinkAction> {
+        self.panes.iter_mut().flat_map(|pane| pane.terminal.take_link_actions()).collect()
+    }
+}
+
+// 領域を分割し、(1つ目, 仕切り, 2つ目) の領域を返す
+fn split_rect(rect: Rect, direction: SplitDirection, ratio: f32) -> (Rect, Rect, Rect) {
+    match direction {
+        SplitDirection::Horizontal => {
+            let x = rect.left() + (rect.width() - DIVIDER_WIDTH) * ratio;
+            let (first, rest) = rect.split_left_right_at_x(x);
+            let (divider, second) = rest.split_left_right_at_x(x + DIVIDER_WIDTH);
+            (first, divider, second)
+        },
+        SplitDirection::Vertical => {
+            let y = rect.top() + (rect.height() - DIVIDER_WIDTH) * ratio;
+            let (first, rest) = rect.split_top_bottom_at_y(y);
+            let (divider, second) = rest.split_top_bottom_at_y(y + DIVIDER_WIDTH);
+            (first, divider, second)
+        },
+    }
+}
+
+fn overlaps(a: egui::Rangef, b: egui::Rangef) -> bool {
+    a.min < b.max && b.min < a.max
+}
+
+// 配置を保存するファイル（$XDG_CONFIG_HOME/alacritty_chat/layout.json）
+fn layout_path() -> io::Result<PathBuf> {
+    Ok(config::config_dir()?.join("layout.json"))
+}
+
+// ペインの配置をファイルに保存する
+pub fn save_layout(layout: &Layout) -> io::Result<PathBuf> {
+    let path = layout_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(layout).map_err(io::Error::other)?;
+    std::fs::write(&path, json)?;
+    Ok(path)
+}
+
+// 保存したペインの配置を読み込む
+pub fn load_layout() -> io::Result<Layout> {
+    let json = std::fs::read_to_string(layout_path()?)?;
+    serde_json::from_str(&json).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn pane(n: u32) -> egui::Id {
+        egui::Id::new(n)
+    }
+    
+    // 木の形を "H(1,V(2,3))" のような文字列にする
+    fn shape(node: &Node) -> String {
+        match node {
+            Node::Pane(id) => (0..10).find(|&n| pane(n) == *id).map_or("?".to_string(), |n| n.to_string()),
+            Node::Split { direction, first, second, .. } => {
+                let direction = match direction {
+                    SplitDirection::Horizontal => "H",
+                    SplitDirection::Vertical => "V",
+                };
+                format!("{}({},{})", direction, shape(first), shape(second))
+            },
+        }
+    }
+    
+    // 1 | (2 / (3 | 4)) の木を作る
+    fn nested() -> Node {
+        let mut root = Node::Pane(pane(1));
+        assert!(root.split(pane(1), pane(2), SplitDirection::Horizontal));
+        assert!(root.split(pane(2), pane(3), SplitDirection::Vertical));
+        assert!(root.split(pane(3), pane(4), SplitDirection::Horizontal));
+        assert!(!root.split(pane(9), pane(5), SplitDirection::Horizontal));
+        root
+    }
+    
+    #[test]
+    fn split_nested_panes() {
+        assert_eq!(shape(&nested()), "H(1,V(2,H(3,4)))");
+    }
+    
+    #[test]
+    fn remove_nested_panes() {
+        // 深い位置の2つ目の子を取り除くと、1つ目の子が親の位置に上がる
+        let mut root = nested();
+        assert!(root.remove(pane(4)));
+        assert_eq!(shape(&root), "H(1,V(2,3))");
+        
+        // 1つ目の子を取り除くと、2つ目の子が親の位置に上がる
+        let mut root = nested();
+        assert!(root.remove(pane(3)));
+        assert_eq!(shape(&root), "H(1,V(2,4))");
+        let mut root = nested();
+        assert!(root.remove(pane(1)));
+        assert_eq!(shape(&root), "V(2,H(3,4))");
+        
+        // 分割されたペインを取り除いても兄弟の部分木はそのまま残る
+        let mut root = nested();
+        assert!(root.remove(pane(2)));
+        assert_eq!(shape(&root), "H(1,H(3,4))");
+        
+        assert!(!root.remove(pane(9)));
+    }
+    
+    #[test]
+    fn remove_only_pane() {
+        // 唯一のペインは取り除けない
+        let mut root = Node::Pane(pane(1));
+        assert!(!root.remove(pane(1)));
+        assert_eq!(shape(&root), "1");
+    }
+}
@@ -0,0 +1,188 @@
+use egui::{Slider, TextEdit, Color32, DragValue, ComboBox};
+use std::path::PathBuf;
+
+use crate::config::{ClipboardPolicy, CursorShape, TerminalConfig};
+use crate::terminal::{self, font};
+
+// ターミナル設定ウィンドウ
+pub struct SettingsWindow {
+    pub open: bool,
+    font_path: String,
+    error: Option<String>,
+    hint_patterns: String, // 1行に1つの正規表現
+    hint_error: Option<String>,
+    editing: bool, // スライダーのドラッグや数値の入力の途中
+    pub file_error: Option<String>, // 設定ファイルの読み書きのエラー
+}
+
+impl SettingsWindow {
+    pub fn new(config: &TerminalConfig) -> Self {
+        let font_path = config
+            .font
+            .path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        
+        Self {
+            open: false,
+            font_path,
+            error: None,
+            hint_patterns: config.hint_patterns.join("\n"),
+            hint_error: None,
+            editing: false,
+            file_error: None,
+        }
+    }
+    
+    // スライダーのドラッグや数値の入力が続いているかどうか
+    //
+    // 値は操作中も反映するが、保存は操作を終えてから行う。
+    pub fn editing(&self) -> bool {
+        self.editing
+    }
+    
+    // 設定ウィンドウを表示し、設定が変更された場合は true を返す
+    pub fn show(&mut self, ctx: &egui::Context, config: &mut TerminalConfig) -> bool {
+        let mut changed = false;
+        let mut editing = false;
+        let mut open = self.open;
+        
+        egui::Window::new("ターミナル設定")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if let Some(error) = &self.file_error {
+                    ui.colored_label(Color32::from_rgb(255, 100, 100), error);
+                    ui.separator();
+                }
+                
+                ui.heading("フォント");
+                
+                ui.horizontal(|ui| {
+                    ui.label("フォントファイル:");
+                    ui.add(TextEdit::singleline(&mut self.font_path).hint_text("組み込みの等幅フォント"));
+                    if ui.button("適用").clicked() {
+                        let path = self.font_path.trim();
+                        config.font.path = (!path.is_empty()).then(|| PathBuf::from(path));
+                        
+                        self.error = font::install(ctx, &config.font)
+                            .err()
+                            .map(|e| format!("フォントの読み込みに失敗しました: {}", e));
+                        changed = true;
+                    }
+                });
+                
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::from_rgb(255, 100, 100), error);
+                }
+                
+                let response = ui.add(Slider::new(&mut config.font.size, 6.0..=48.0).text("サイズ"));
+                changed |= response.changed();
+                editing |= is_editing(&response);
+                let response = ui.add(Slider::new(&mut config.font.line_spacing, 1.0..=2.0).text("行間"));
+                changed |= response.changed();
+                editing |= is_editing(&response);
+                
+                ui.separator();
+                ui.heading("表示");
+                
+                changed |= ui
+                    .checkbox(&mut config.draw_bold_text_with_bright_colors, "太字を明るい色で表示")
+                    .changed();
+                
+                ui.horizontal(|ui| {
+                    ui.label("スクロールバック:");
+                    let response = ui.add(DragValue::new(&mut config.scrollback_lines).range(0..=100_000).suffix(" 行"));
+                    changed |= response.changed();
+                    editing |= is_editing(&response);
+                });
+                
+                ui.separator();
+                ui.heading("カーソル");
+                
+                ComboBox::from_label("形状")
+                    .selected_text(config.cursor.shape.label())
+                    .show_ui(ui, |ui| {
+                        for shape in CursorShape::ALL {
+                            changed |= ui
+                                .selectable_value(&mut config.cursor.shape, shape, shape.label())
+                                .changed();
+                        }
+                    });
+                changed |= ui.checkbox(&mut config.cursor.blinking, "点滅する").changed();
+                let response = ui.add(Slider::new(&mut config.cursor.blink_interval_ms, 100..=2000).text("点滅間隔（ミリ秒）"));
+                changed |= response.changed();
+                editing |= is_editing(&response);
+                
+                ui.separator();
+                ui.heading("入力");
+                
+                changed |= ui
+                    .checkbox(&mut config.confirm_unsafe_paste, "複数行の貼り付けを確認する")
+                    .changed();
+                
+                ui.separator();
+                ui.heading("クリップボード（OSC 52）");
+                
+                changed |= policy_combo_box(ui, "書き込み", &mut config.osc52_copy);
+                changed |= policy_combo_box(ui, "読み取り", &mut config.osc52_paste);
+                
+                ui.separator();
+                ui.heading("ヒント");
+                
+                ui.label("URL・パス・コミットハッシュ・IPアドレスに加えて検出する正規表現（1行に1つ）:");
+                ui.add(
+                    TextEdit::multiline(&mut self.hint_patterns)
+                        .desired_rows(3)
+                        .code_editor()
+                        .hint_text("例: JIRA-[0-9]+"),
+                );
+                if ui.button("適用").clicked() {
+                    let patterns: Vec<String> = self
+                        .hint_patterns
+                        .lines()
+                        .map(str::trim)
+                        .filter(|pattern| !pattern.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    
+                    // 不正なパターンがあれば適用しない
+                    self.hint_error = patterns.iter().find_map(|pattern| {
+                        terminal::check_hint_pattern(pattern)
+                            .err()
+                            .map(|e| format!("不正な正規表現です: {}\n{}", pattern, e))
+                    });
+                    if self.hint_error.is_none() {
+                        config.hint_patterns = patterns;
+                        changed = true;
+                    }
+                }
+                if let Some(error) = &self.hint_error {
+                    ui.colored_label(Color32::from_rgb(255, 100, 100), error);
+                }
+            });
+        
+        self.open = open;
+        self.editing = editing;
+        changed
+    }
+}
+
+// ドラッグ中か、数値を入力中（フォーカスがある）かどうか
+fn is_editing(response: &egui::Response) -> bool {
+    response.dragged() || response.has_focus()
+}
+
+// クリップボード操作の許可設定を選ぶコンボボックス
+fn policy_combo_box(ui: &mut egui::Ui, label: &str, policy: &mut ClipboardPolicy) -> bool {
+    let mut changed = false;
+    ComboBox::from_label(label)
+        .selected_text(policy.label())
+        .show_ui(ui, |ui| {
+            for value in ClipboardPolicy::ALL {
+                changed |= ui.selectable_value(policy, value, value.label()).changed();
+            }
+        });
+    changed
+}
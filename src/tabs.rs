@@ -0,0 +1,378 @@
+use std::io;
+use std::path::PathBuf;
+use egui::{Key, Modal, Modifiers, Sense, TextEdit};
+
+use crate::config::TerminalConfig;
+use crate::panes::{self, Layout, PaneTree};
+use crate::terminal::LinkAction;
+
+// タブの名前として表示する最大文字数
+const MAX_TITLE_CHARS: usize = 24;
+
+// ターミナルのタブ
+struct Tab {
+    id: egui::Id,
+    panes: PaneTree,
+    name: Option<String>, // ユーザーが付けた名前
+}
+
+impl Tab {
+    // タブに表示する名前（ユーザーが付けた名前、端末のタイトルの順に使う）
+    fn title(&self) -> &str {
+        self.name
+            .as_deref()
+            .or_else(|| self.panes.active_terminal().title())
+            .unwrap_or("ターミナル")
+    }
+}
+
+// タブバーでの操作
+enum TabCommand {
+    Add,
+    Select(usize),
+    Close(usize),
+    Move(usize, usize), // (移動元, 移動先)
+    StartRename(usize),
+    Rename(usize, Option<String>),
+    CancelRename,
+}
+
+// 閉じる確認中のタブまたはペイン
+struct PendingClose {
+    tab: egui::Id,
+    pane_only: bool, // タブではなく選択中のペインだけを閉じる
+    job: String, // 実行中のコマンド
+}
+
+// タブごとに独立したシェルを持つターミナル
+//
+// Ctrl+Shift+T で新しいタブを開き、Ctrl+Shift+W で選択中のペイン（最後のペインならタブ）を閉じる。
+// Ctrl+PageUp/PageDown（Ctrl+Tab）で切り替え、Ctrl+Shift+PageUp/PageDown で並べ替える。
+// Alt+1～9 で指定した番号のタブに切り替える。
+pub struct TerminalTabs {
+    tabs: Vec<Tab>,
+    active: usize,
+    next_id: u64,
+    renaming: Option<(egui::Id, String)>, // 名前を変更中のタブと入力中の名前
+    pending_close: Option<PendingClose>,
+}
+
+impl TerminalTabs {
+    pub fn new(ctx: &egui::Context, config: &TerminalConfig) -> Self {
+        let mut tabs = Self {
+            tabs: Vec::new(),
+            active: 0,
+            next_id: 0,
+            renaming: None,
+            pending_close: None,
+        };
+        tabs.add_tab(ctx, config);
+        tabs
+    }
+    
+    // 新しいタブを開いて選択する
+    fn add_tab(&mut self, ctx: &egui::Context, config: &TerminalConfig) {
+        self.add_tab_with_layout(ctx, config, None);
+    }
+    
+    // 指定したペインの配置で新しいタブを開く
+    fn add_tab_with_layout(&mut self, ctx: &egui::Context, config: &TerminalConfig, layout: Option<&Layout>) {
+        let id = egui::Id::new(("terminal_tab", self.next_id));
+        let panes = match layout {
+            Some(layout) => PaneTree::from_layout(ctx, config, id, layout),
+            None => PaneTree::new(ctx, config, id),
+        };
+        self.tabs.push(Tab { id, panes, name: None });
+        self.next_id += 1;
+        self.active = self.tabs.len() - 1;
+    }
+    
+    // タブを選択し、ターミナルにフォーカスを移す
+    fn select(&mut self, index: usize) {
+        if let Some(tab) = self.tabs.get_mut(index) {
+            tab.panes.request_focus();
+            self.active = index;
+        }
+    }
+    
+    // タブを閉じる。フォアグラウンドでコマンドが実行中なら確認する
+    fn request_close(&mut self, ctx: &egui::Context, config: &TerminalConfig, index: usize) {
+        let Some(tab) = self.tabs.get(index) else {
+            return;
+        };
+        
+        match tab.panes.foreground_job() {
+            Some(job) => self.pending_close = Some(PendingClose { tab: tab.id, pane_only: false, job }),
+            None => self.close(ctx, config, index),
+        }
+    }
+    
+    // 選択中のタブの選択中のペインを閉じる。最後のペインの場合はタブを閉じる
+    fn request_close_pane(&mut self, ctx: &egui::Context, config: &TerminalConfig) {
+        let tab = &mut self.tabs[self.active];
+        if tab.panes.pane_count() <= 1 {
+            self.request_close(ctx, config, self.active);
+            return;
+        }
+        
+        match tab.panes.active_terminal().foreground_job() {
+            Some(job) => self.pending_close = Some(PendingClose { tab: tab.id, pane_only: true, job }),
+            None => tab.panes.close_active(),
+        }
+    }
+    
+    fn close(&mut self, ctx: &egui::Context, config: &TerminalConfig, index: usize) {
+        self.tabs.remove(index);
+        
+        // 最後のタブを閉じた場合は新しいシェルを開く
+        if self.tabs.is_empty() {
+            self.add_tab(ctx, config);
+            return;
+        }
+        
+        if index < self.active || self.active == self.tabs.len() {
+            self.active -= 1;
+        }
+        self.select(self.active);
+    }
+    
+    // タブを並べ替える
+    fn move_tab(&mut self, from: usize, to: usize) {
+        if from == to || from >= self.tabs.len() || to >= self.tabs.len() {
+            return;
+        }
+        
+        let active_id = self.tabs[self.active].id;
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+        self.active = self.tabs.iter().position(|tab| tab.id == active_id).unwrap_or(to);
+    }
+    
+    // タブバーと選択中のタブのターミナルを表示する
+    pub fn ui(&mut self, ui: &mut egui::Ui, config: &TerminalConfig) {
+        // ショートカットはターミナルにフォーカスがあるときだけ使う
+        // （エディタのウィンドウやチャットの入力欄での入力は奪わない）
+        if self.tabs[self.active].panes.has_focus(ui.ctx()) {
+            self.handle_shortcuts(ui, config);
+        }
+        
+        // 表示していないタブも端末からのイベントを処理する
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            if index != self.active {
+                tab.panes.process_events(ui.ctx());
+            }
+        }
+        
+        self.tab_bar_ui(ui, config);
+        ui.separator();
+        
+        let tab = &mut self.tabs[self.active];
+        ui.push_id(tab.id, |ui| tab.panes.ui(ui, config));
+        
+        self.close_confirmation_ui(ui.ctx(), config);
+    }
+    
+    // タブを操作するキーボードショートカット
+    //
+    // Shift付きのショートカットを先に調べる（Shiftなしのものにも一致するため）。
+    fn handle_shortcuts(&mut self, ui: &egui::Ui, config: &TerminalConfig) {
+        let ctrl_shift = Modifiers::CTRL | Modifiers::SHIFT;
+        let consume = |modifiers, key| ui.input_mut(|i| i.consume_key(modifiers, key));
+        
+        if consume(ctrl_shift, Key::T) {
+            self.add_tab(ui.ctx(), config);
+        }
+        if consume(ctrl_shift, Key::W) {
+            self.request_close_pane(ui.ctx(), config);
+        }
+        
+        let count = self.tabs.len();
+        if consume(ctrl_shift, Key::PageUp) {
+            self.move_tab(self.active, self.active.saturating_sub(1));
+        }
+        if consume(ctrl_shift, Key::PageDown) {
+            self.move_tab(self.active, (self.active + 1).min(count - 1));
+        }
+        if consume(ctrl_shift, Key::Tab) || consume(Modifiers::CTRL, Key::PageUp) {
+            self.select((self.active + count - 1) % count);
+        }
+        if consume(Modifiers::CTRL, Key::Tab) || consume(Modifiers::CTRL, Key::PageDown) {
+            self.select((self.active + 1) % count);
+        }
+        
+        let number_keys = [
+            Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+        ];
+        // タブが1つのときは Alt+数字 をシェルに送る（readline の数引数など）。
+        // Alt+Shift+数字 は別のキーとして扱う
+        let alt_only = ui.input(|i| i.modifiers.matches_exact(Modifiers::ALT));
+        for (index, key) in number_keys.into_iter().enumerate() {
+            if count > 1 && alt_only && index < count && consume(Modifiers::ALT, key) {
+                self.select(index);
+            }
+        }
+    }
+    
+    // タブバー
+    //
+    // ダブルクリックで名前を変更し、ドラッグで並べ替え、中クリックで閉じる。
+    fn tab_bar_ui(&mut self, ui: &mut egui::Ui, config: &TerminalConfig) {
+        let mut command = None;
+        
+        ui.horizontal(|ui| {
+            for (index, tab) in self.tabs.iter().enumerate() {
+                // 名前の変更中は入力欄を表示する
+                if let Some((id, name)) = &mut self.renaming {
+                    if *id == tab.id {
+                        let input = ui.add(TextEdit::singleline(name).desired_width(120.0));
+                        input.request_focus();
+                        if input.lost_focus() {
+                            // Escのときだけ取り消し、Enterや他の場所のクリックでは確定する
+                            if ui.input(|i| i.key_pressed(Key::Escape)) {
+                                command = Some(TabCommand::CancelRename);
+                            } else {
+                                let name = name.trim().to_string();
+                                command = Some(TabCommand::Rename(index, (!name.is_empty()).then_some(name)));
+                            }
+                        }
+                        continue;
+                    }
+                }
+                
+                let title: String = tab.title().chars().take(MAX_TITLE_CHARS).collect();
+                let response = ui
+                    .selectable_label(index == self.active, title)
+                    .interact(Sense::click_and_drag())
+                    .on_hover_text(tab.title());
+                
+                if response.clicked() {
+                    command = Some(TabCommand::Select(index));
+                }
+                if response.double_clicked() {
+                    command = Some(TabCommand::StartRename(index));
+                }
+                if response.middle_clicked() {
+                    command = Some(TabCommand::Close(index));
+                }
+                
+                // ドラッグしたタブを別のタブの位置に落とすと並べ替える
+                response.dnd_set_drag_payload(index);
+                if let Some(from) = response.dnd_release_payload::<usize>() {
+                    command = Some(TabCommand::Move(*from, index));
+                }
+                
+                response.context_menu(|ui| {
+                    if ui.button("名前を変更").clicked() {
+                        command = Some(TabCommand::StartRename(index));
+                        ui.close_menu();
+                    }
+                    if ui.button("閉じる").clicked() {
+                        command = Some(TabCommand::Close(index));
+                        ui.close_menu();
+                    }
+                });
+                
+                if ui.small_button("✕").on_hover_text("タブを閉じる (Ctrl+Shift+W)").clicked() {
+                    command = Some(TabCommand::Close(index));
+                }
+            }
+            
+            if ui.button("+").on_hover_text("新しいタブ (Ctrl+Shift+T)").clicked() {
+                command = Some(TabCommand::Add);
+            }
+        });
+        
+        match command {
+            Some(TabCommand::Add) => self.add_tab(ui.ctx(), config),
+            Some(TabCommand::Select(index)) => self.select(index),
+            Some(TabCommand::Close(index)) => self.request_close(ui.ctx(), config, index),
+            Some(TabCommand::Move(from, to)) => self.move_tab(from, to),
+            Some(TabCommand::StartRename(index)) => {
+                let tab = &self.tabs[index];
+                self.renaming = Some((tab.id, tab.title().to_string()));
+            },
+            Some(TabCommand::Rename(index, name)) => {
+                self.tabs[index].name = name;
+                self.renaming = None;
+                self.select(index);
+            },
+            Some(TabCommand::CancelRename) => {
+                self.renaming = None;
+                self.select(self.active);
+            },
+            None => {},
+        }
+    }
+    
+    // 実行中のコマンドがあるタブを閉じる前の確認ダイアログ
+    fn close_confirmation_ui(&mut self, ctx: &egui::Context, config: &TerminalConfig) {
+        let Some(pending) = &self.pending_close else {
+            return;
+        };
+        let Some(index) = self.tabs.iter().position(|tab| tab.id == pending.tab) else {
+            self.pending_close = None;
+            return;
+        };
+        
+        let mut confirmed = false;
+        let mut cancelled = false;
+        
+        let modal = Modal::new(egui::Id::new("close_tab_confirmation")).show(ctx, |ui| {
+            ui.set_max_width(400.0);
+            let target = if pending.pane_only { "ペイン" } else { "タブ" };
+            ui.heading(format!("{}を閉じる", target));
+            ui.label(format!("「{}」が実行中です。\n{}を閉じると実行中のコマンドも終了します。", pending.job, target));
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                confirmed = ui.button("閉じる").clicked();
+                cancelled = ui.button("キャンセル").clicked();
+            });
+        });
+        
+        if modal.should_close() {
+            cancelled = true;
+        }
+        
+        if confirmed {
+            if let Some(pending) = self.pending_close.take() {
+                if pending.pane_only {
+                    self.tabs[index].panes.close_active();
+                } else {
+                    self.close(ctx, config, index);
+                }
+            }
+        } else if cancelled {
+            self.pending_close = None;
+        }
+    }
+    
+    // 選択中のタブの名前（ウィンドウタイトル用）
+    pub fn title(&self) -> Option<&str> {
+        let tab = &self.tabs[self.active];
+        tab.name.as_deref().or_else(|| tab.panes.active_terminal().title())
+    }
+    
+    // 設定をすべてのタブに反映する
+    pub fn set_config(&mut self, config: &TerminalConfig) {
+        for tab in &mut self.tabs {
+            tab.panes.set_config(config);
+        }
+    }
+    
+    // すべてのタブで操作されたリンクを取り出す
+    pub fn take_link_actions(&mut self) -> Vec<LinkAction> {
+        self.tabs.iter_mut().flat_map(|tab| tab.panes.take_link_actions()).collect()
+    }
+    
+    // 選択中のタブのペインの配置をファイルに保存する
+    pub fn save_layout(&self) -> io::Result<PathBuf> {
+        panes::save_layout(&self.tabs[self.active].panes.layout())
+    }
+    
+    // 保存したペインの配置を新しいタブに復元する
+    pub fn restore_layout(&mut self, ctx: &egui::Context, config: &TerminalConfig) -> io::Result<()> {
+        let layout = panes::load_layout()?;
+        self.add_tab_with_layout(ctx, config, Some(&layout));
+        Ok(())
+    }
+}
@@ -1,225 +1,266 @@
-use egui::{Color32, Align2, Rect, Vec2, Pos2};
+use egui::{Color32, Rect, Vec2, Pos2, Stroke, StrokeKind};
+use egui::text::{LayoutJob, TextFormat};
 use std::{
-    sync::mpsc,
-    thread,
-    time::Duration,
-    process::{Command, Stdio},
-    io::{Read, Write},
-    env,
+    borrow::Cow,
+    path::PathBuf,
+    sync::{mpsc, Arc},
+    time::{Duration, Instant},
 };
+use alacritty_terminal::event::{Event, EventListener, Notify, OnResize, WindowSize};
+use alacritty_terminal::event_loop::{EventLoop, Msg, Notifier};
+use alacritty_terminal::grid::{Dimensions, Scroll};
+use alacritty_terminal::sync::FairMutex;
+use alacritty_terminal::term::{self, Term, TermMode};
+use alacritty_terminal::term::color::Colors;
+use alacritty_terminal::term::cell::{Cell, Flags};
+use alacritty_terminal::vte::ansi::{self, Color, CursorShape, CursorStyle, NamedColor};
 
-// ターミナルの色設定
-const COLORS: [Color32; 16] = [
-    Color32::from_rgb(0, 0, 0),       // 黒 (背景色)
-    Color32::from_rgb(205, 0, 0),     // 赤
-    Color32::from_rgb(0, 205, 0),     // 緑
-    Color32::from_rgb(205, 205, 0),   // 黄
-    Color32::from_rgb(0, 0, 238),     // 青
-    Color32::from_rgb(205, 0, 205),   // マゼンタ
-    Color32::from_rgb(0, 205, 205),   // シアン
-    Color32::from_rgb(229, 229, 229), // 白
-    Color32::from_rgb(127, 127, 127), // 明るい黒
-    Color32::from_rgb(255, 0, 0),     // 明るい赤
-    Color32::from_rgb(0, 255, 0),     // 明るい緑
-    Color32::from_rgb(255, 255, 0),   // 明るい黄
-    Color32::from_rgb(92, 92, 255),   // 明るい青
-    Color32::from_rgb(255, 0, 255),   // 明るいマゼンタ
-    Color32::from_rgb(0, 255, 255),   // 明るいシアン
-    Color32::from_rgb(255, 255, 255), // 明るい白
-];
+mod clipboard;
+mod colors;
+pub mod font;
+mod hints;
+mod ime;
+mod keys;
+mod links;
+mod mouse;
+mod paste;
+mod pty;
+mod search;
+mod shell_integration;
+mod vi;
 
-enum TerminalEvent {
-    Input(Vec<u8>),
+use clipboard::ClipboardRequest;
+use colors::Palette;
+use hints::{HintAction, HintState};
+pub use hints::check_hint_pattern;
+use links::{Link, LinkDetector};
+pub use links::LinkAction;
+use mouse::MouseState;
+use pty::{spawn_pty, ExtraModes, ModeTrackingPty};
+use search::SearchState;
+use shell_integration::{CommandLog, GUTTER_WIDTH};
+use vi::ViState;
+use crate::config::{self, TerminalConfig};
+
+// ウィンドウのリサイズ中にPTYへ通知しすぎないための待ち時間
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+// スクロールバーの幅と、つまみの最小の高さ（ピクセル）
+const SCROLLBAR_WIDTH: f32 = 8.0;
+const SCROLLBAR_MIN_THUMB: f32 = 20.0;
+
+// 太字を表現するための重ね描きのずらし幅（ピクセル）
+const BOLD_OFFSET: f32 = 0.8;
+
+// 端末エミュレータからのイベントをUIスレッドへ転送する
+#[derive(Clone)]
+struct EventProxy {
+    tx: mpsc::Sender<Event>,
+    ctx: egui::Context,
+}
+
+impl EventListener for EventProxy {
+    fn send_event(&self, event: Event) {
+        let _ = self.tx.send(event);
+        // PTYスレッドから新しい出力が届いたら再描画を要求
+        self.ctx.request_repaint();
+    }
+}
+
+// グリッドの大きさ（列数と行数）
+#[derive(Clone, Copy)]
+struct TermSize {
+    columns: usize,
+    screen_lines: usize,
+}
+
+impl Dimensions for TermSize {
+    fn total_lines(&self) -> usize {
+        self.screen_lines
+    }
+
+    fn screen_lines(&self) -> usize {
+        self.screen_lines
+    }
+
+    fn columns(&self) -> usize {
+        self.columns
+    }
+}
+
+// 端末で起動するプログラム
+#[derive(Debug, Clone)]
+pub struct ShellCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    // 作業ディレクトリ。None の場合はこのアプリケーションと同じ
+    pub working_directory: Option<PathBuf>,
+}
+
+impl ShellCommand {
+    // 環境変数 SHELL のシェル
+    pub fn shell() -> Self {
+        let program = std::env::var("SHELL").unwrap_or_else(|_| {
+            if cfg!(target_os = "windows") {
+                "cmd.exe".to_string()
+            } else {
+                "/bin/bash".to_string()
+            }
+        });
+        
+        Self {
+            program,
+            args: Vec::new(),
+            working_directory: None,
+        }
+    }
 }
 
 pub struct TerminalPane {
-    event_tx: mpsc::Sender<TerminalEvent>,
-    output_rx: mpsc::Receiver<String>,
-    buffer: Vec<String>,
-    cursor_pos: (usize, usize), // (column, row)
+    term: Arc<FairMutex<Term<EventProxy>>>,
+    notifier: Option<Notifier>,
+    child_pid: Option<u32>, // シェルのプロセスID
+    event_rx: mpsc::Receiver<Event>,
+    extra_modes: ExtraModes,
+    commands: CommandLog, // シェル統合（OSC 133）で記録したコマンドの実行状態
     size: (u16, u16), // (cols, rows)
+    pending_resize: Option<((u16, u16), Instant)>, // PTYへの通知待ちのサイズ
+    scroll_accumulator: f32, // 1行に満たないホイールの移動量
+    mouse: MouseState,
+    pending_paste: Option<String>, // 確認待ちの貼り付けテキスト
+    pending_clipboard: Option<ClipboardRequest>, // 確認待ちのOSC 52の要求
+    preedit: String, // IMEで変換中の未確定文字列
+    cursor_blink_start: Instant, // カーソルの点滅を開始した時刻
+    title: Option<String>, // OSC 0/2 で設定されたタイトル
+    link_detector: LinkDetector,
+    hovered_link: Option<Link>, // ポインタ下のリンク
+    menu_link: Option<Link>, // メニューを表示中のリンク
+    link_actions: Vec<LinkAction>, // アプリケーション側で処理するリンクの操作
+    search: SearchState,
+    vi: ViState,
+    hints: HintState,
     cell_size: (f32, f32), // (width, height)in pixels
+    glyph_offset: f32, // セル内でのグリフの縦方向のオフセット
+    palette: Palette,
+    config: TerminalConfig,
     focused: bool,
+    focus_requested: bool, // 次のフレームでキーボードフォーカスを移す
+    widget_id: Option<egui::Id>, // 最後に表示したときのウィジェットのID
 }
 
 impl TerminalPane {
-    pub fn new() -> Self {
+    // 指定したプログラムを起動する端末を作成する
+    pub fn with_command(ctx: &egui::Context, config: TerminalConfig, command: ShellCommand) -> Self {
         // 端末の初期サイズ（列数と行数）
         let cols = 80;
         let rows = 24;
         
-        // フォントサイズに基づいたセルのサイズ
-        let cell_width = 8.0;  // 仮の値、実際にはフォントメトリクスから計算
-        let cell_height = 16.0; // 仮の値
+        // セルのサイズの初期値（最初のフレームでフォントメトリクスから計算し直す）
+        let cell_width = 8.0;
+        let cell_height = 16.0;
         
-        // イベント送信用のチャネル
+        // イベント受信用のチャネル
         let (event_tx, event_rx) = mpsc::channel();
-        let (output_tx, output_rx) = mpsc::channel();
+        let event_proxy = EventProxy { tx: event_tx, ctx: ctx.clone() };
         
-        // 環境変数からシェルを取得
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| {
-            if cfg!(target_os = "windows") {
-                "cmd.exe".to_string()
-            } else {
-                "/bin/bash".to_string()
-            }
-        });
+        // VTEパーサーの出力先となる端末グリッド
+        let size = TermSize { columns: cols as usize, screen_lines: rows as usize };
+        let term = Term::new(term_config(&config), &size, event_proxy.clone());
+        let term = Arc::new(FairMutex::new(term));
         
-        // ターミナルスレッドを起動
-        thread::spawn(move || {
-            // 環境変数を設定
-            let mut command = Command::new(&shell);
-            command.stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped());
-            
-            // 環境変数を設定して日本語対応
-            let path = env::var("PATH").unwrap_or_default();
-            let home = env::var("HOME").unwrap_or_default();
-            
-            command.env("PATH", path)
-                   .env("HOME", home)
-                   .env("LANG", "ja_JP.UTF-8")
-                   .env("LC_ALL", "ja_JP.UTF-8")
-                   .env("TERM", "xterm-256color");
-            
-            // シェルプロセスを起動
-            let mut child = match command.spawn() {
-                Ok(child) => {
-                    // 成功したら情報をログに出力
-                    output_tx.send(format!("ターミナルを起動しました: {}\n", shell)).ok();
-                    child
-                },
-                Err(e) => {
-                    // 詳細なエラー情報を送信
-                    let error_msg = format!("シェルの起動に失敗しました: {}\nパス: {}\n", e, shell);
-                    output_tx.send(error_msg).ok();
-                    
-                    // フォールバック: もしbashが失敗したら他のシェルを試す
-                    if shell == "/bin/bash" {
-                        // shを試す
-                        match Command::new("/bin/sh")
-                            .stdin(Stdio::piped())
-                            .stdout(Stdio::piped())
-                            .stderr(Stdio::piped())
-                            .spawn() {
-                                Ok(child) => {
-                                    output_tx.send("フォールバック: /bin/shを使用します\n".to_string()).ok();
-                                    child
-                                },
-                                Err(e2) => {
-                                    output_tx.send(format!("すべてのシェルが失敗しました: {}\n", e2)).ok();
-                                    return;
-                                }
-                            }
-                    } else {
-                        return;
-                    }
-                }
-            };
-            
-            // 標準入力へのハンドルを取得
-            let mut stdin = match child.stdin.take() {
-                Some(stdin) => stdin,
-                None => {
-                    output_tx.send("子プロセスのstdinを取得できません\n".to_string()).ok();
-                    return;
-                }
-            };
-            
-            // 標準出力を読み取るスレッドを起動
-            let mut stdout = match child.stdout.take() {
-                Some(stdout) => stdout,
-                None => {
-                    output_tx.send("子プロセスのstdoutを取得できません\n".to_string()).ok();
-                    return;
-                }
-            };
-            
-            let output_tx_clone = output_tx.clone();
-            
-            thread::spawn(move || {
-                let mut buffer = [0; 1024];
-                loop {
-                    match stdout.read(&mut buffer) {
-                        Ok(0) => break, // EOFに達した
-                        Ok(n) => {
-                            match String::from_utf8(buffer[..n].to_vec()) {
-                                Ok(s) => {
-                                    output_tx_clone.send(s).ok();
-                                },
-                                Err(_) => {
-                                    // UTF-8でデコードできない場合は、lossy変換を使用
-                                    let s = String::from_utf8_lossy(&buffer[..n]).to_string();
-                                    output_tx_clone.send(s).ok();
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            output_tx_clone.send(format!("stdout読み取りエラー: {}\n", e)).ok();
-                            break;
-                        }
-                    }
-                }
-            });
-            
-            // 標準エラー出力を読み取るスレッド
-            let mut stderr = match child.stderr.take() {
-                Some(stderr) => stderr,
-                None => {
-                    output_tx.send("子プロセスのstderrを取得できません\n".to_string()).ok();
-                    return;
-                }
-            };
-            
-            thread::spawn(move || {
-                let mut buffer = [0; 1024];
-                loop {
-                    match stderr.read(&mut buffer) {
-                        Ok(0) => break, // EOFに達した
-                        Ok(n) => {
-                            match String::from_utf8(buffer[..n].to_vec()) {
-                                Ok(s) => {
-                                    output_tx.send(s).ok();
-                                },
-                                Err(_) => {
-                                    // UTF-8でデコードできない場合は、lossy変換を使用
-                                    let s = String::from_utf8_lossy(&buffer[..n]).to_string();
-                                    output_tx.send(s).ok();
-                                }
-                            }
+        let window_size = WindowSize {
+            num_lines: rows,
+            num_cols: cols,
+            cell_width: cell_width as u16,
+            cell_height: cell_height as u16,
+        };
+        
+        // PTYを開いてシェルをセッションリーダーとして起動
+        let pty = match spawn_pty(&command, window_size) {
+            Ok(pty) => {
+                // 成功したら情報をログに出力
+                write_message(&term, &format!("ターミナルを起動しました: {}\n", command.program));
+                Some(pty)
+            },
+            Err(e) => {
+                // 詳細なエラー情報を表示
+                let error_msg = format!("シェルの起動に失敗しました: {}\nパス: {}\n", e, command.program);
+                write_message(&term, &error_msg);
+                
+                // フォールバック: もしbashが失敗したら他のシェルを試す
+                if command.program == "/bin/bash" {
+                    // shを試す
+                    let fallback = ShellCommand { program: "/bin/sh".to_string(), ..command.clone() };
+                    match spawn_pty(&fallback, window_size) {
+                        Ok(pty) => {
+                            write_message(&term, "フォールバック: /bin/shを使用します\n");
+                            Some(pty)
                         },
-                        Err(e) => {
-                            output_tx.send(format!("stderr読み取りエラー: {}\n", e)).ok();
-                            break;
+                        Err(e2) => {
+                            write_message(&term, &format!("すべてのシェルが失敗しました: {}\n", e2));
+                            None
                         }
                     }
+                } else {
+                    None
                 }
-            });
-            
-            // キー入力を処理するループ
-            for event in event_rx {
-                match event {
-                    TerminalEvent::Input(data) => {
-                        if stdin.write_all(&data).is_err() {
-                            break;
-                        }
-                        if stdin.flush().is_err() {
-                            break;
-                        }
-                    }
+            }
+        };
+        
+        // 作業ディレクトリを調べるためにシェルのプロセスIDを控えておく
+        #[cfg(unix)]
+        let child_pid = pty.as_ref().map(|pty| pty.child().id());
+        #[cfg(not(unix))]
+        let child_pid = None;
+        
+        // PTYの読み書きとVTEパーサーの駆動はalacritty_terminalのイベントループに任せる
+        let extra_modes = ExtraModes::default();
+        let commands = CommandLog::default();
+        let notifier = pty.and_then(|pty| {
+            let pty = ModeTrackingPty::new(pty, extra_modes.clone(), commands.clone());
+            match EventLoop::new(term.clone(), event_proxy, pty, false, false) {
+                Ok(event_loop) => {
+                    let notifier = Notifier(event_loop.channel());
+                    event_loop.spawn();
+                    Some(notifier)
+                },
+                Err(e) => {
+                    write_message(&term, &format!("イベントループの起動に失敗しました: {}\n", e));
+                    None
                 }
             }
         });
         
         Self {
-            event_tx,
-            output_rx,
-            buffer: vec![String::new(); rows as usize],
-            cursor_pos: (0, 0),
+            term,
+            notifier,
+            child_pid,
+            event_rx,
+            extra_modes,
+            commands,
             size: (cols, rows),
+            pending_resize: None,
+            scroll_accumulator: 0.0,
+            mouse: MouseState::default(),
+            pending_paste: None,
+            pending_clipboard: None,
+            preedit: String::new(),
+            cursor_blink_start: Instant::now(),
+            title: None,
+            link_detector: LinkDetector::new(),
+            hovered_link: None,
+            menu_link: None,
+            link_actions: Vec::new(),
+            search: SearchState::default(),
+            vi: ViState::default(),
+            hints: HintState::new(&config.hint_patterns),
             cell_size: (cell_width, cell_height),
+            glyph_offset: 0.0,
+            palette: Palette::new(),
+            config,
             focused: false,
+            focus_requested: false,
+            widget_id: None,
         }
     }
     
@@ -229,229 +270,752 @@ impl TerminalPane {
             ui.available_size(),
             egui::Sense::click_and_drag(),
         );
+        self.widget_id = Some(response.id);
+        
+        if response.gained_focus() {
+            self.cursor_blink_start = Instant::now();
+        }
         
-        if response.clicked() {
+        if response.clicked() || self.focus_requested {
             self.focused = true;
+            self.focus_requested = false;
             // フォーカスを設定
             ui.memory_mut(|mem| mem.request_focus(response.id));
         }
         
+        // フォントメトリクスからセルのサイズを求める
+        let font_id = font::font_id(ui, &self.config.font);
+        let (cell_width, cell_height, glyph_offset) =
+            font::cell_metrics(ui, &font_id, self.config.font.line_spacing);
+        self.cell_size = (cell_width, cell_height);
+        self.glyph_offset = glyph_offset;
+        
         let painter = ui.painter();
-        let rect = response.rect;
+        
+        // 右端にスクロールバーの領域を確保し、残りをグリッドに使う
+        let (rect, scrollbar_rect) = response
+            .rect
+            .split_left_right_at_x(response.rect.right() - SCROLLBAR_WIDTH);
+        // 左端にはシェル統合のマークを表示する余白を確保する
+        let (gutter_rect, rect) = rect.split_left_right_at_x(rect.left() + GUTTER_WIDTH);
         
         // 現在のレンダリング領域のサイズから端末のサイズを再計算
         let new_cols = (rect.width() / self.cell_size.0).floor() as u16;
         let new_rows = (rect.height() / self.cell_size.1).floor() as u16;
         
-        // 端末サイズが変更された場合はバッファサイズを調整
-        if new_cols != self.size.0 || new_rows != self.size.1 {
-            if new_cols > 0 && new_rows > 0 {
-                self.size = (new_cols, new_rows);
-                
-                // バッファサイズを調整
-                if self.buffer.len() < new_rows as usize {
-                    self.buffer.resize(new_rows as usize, String::new());
-                }
+        // 端末サイズが変更された場合は、サイズが落ち着くのを待ってから反映する
+        if new_cols > 0 && new_rows > 0 {
+            let new_size = (new_cols, new_rows);
+            if new_size == self.size {
+                self.pending_resize = None;
+            } else if self.pending_resize.map(|(size, _)| size) != Some(new_size) {
+                self.pending_resize = Some((new_size, Instant::now()));
+            }
+        }
+        
+        if let Some((size, since)) = self.pending_resize {
+            let elapsed = since.elapsed();
+            if elapsed >= RESIZE_DEBOUNCE {
+                self.pending_resize = None;
+                self.resize(size);
+            } else {
+                ui.ctx().request_repaint_after(RESIZE_DEBOUNCE - elapsed);
             }
         }
         
-        // 出力を読み取り、バッファに追加
-        self.process_output();
+        // 端末からのイベントを処理
+        self.process_events(ui.ctx());
+        self.hold_vi_view();
+        self.prune_commands();
+        
+        // アプリケーションがマウス報告を要求している場合はマウス操作をPTYへ送る。
+        // Shiftを押している間はローカルの選択操作を優先する
+        let shift = ui.input(|i| i.modifiers.shift);
+        let mouse_reporting = !shift && self.mouse_reporting();
+        
+        // マウスホイールとスクロールバーによるスクロールバック
+        if response.hovered() {
+            self.handle_scroll_wheel(ui, rect, mouse_reporting);
+        }
+        self.scrollbar_ui(ui, response.id.with("scrollbar"), scrollbar_rect);
+        
+        // ポインタ下のリンクを検出し、Ctrl+クリックで開く
+        let link_clicked = self.handle_links(ui, &response, rect);
+        
+        if link_clicked {
+            // リンクを開いたクリックは他の操作に使わない
+        } else if mouse_reporting {
+            self.report_mouse(ui, &response, rect);
+        } else {
+            // マウスによるテキスト選択
+            self.handle_selection(ui, &response, rect);
+        }
+        
+        // ヒントモード中は表示中のテキストからヒントを探す
+        self.update_hints();
+        
+        let term = self.term.lock();
+        let content = term.renderable_content();
+        let display_offset = content.display_offset as i32;
+        let background = self.palette.resolve(Color::Named(NamedColor::Background), content.colors);
         
         // 背景を描画
-        painter.rect_filled(rect, 0.0, COLORS[0]);
+        painter.rect_filled(response.rect, 0.0, background);
+        
+        let cursor = content.cursor;
+        let selection = content.selection;
         
-        // 各行を描画
-        for (row, line) in self.buffer.iter().enumerate().take(self.size.1 as usize) {
+        // 表示範囲にある検索の一致
+        let search_matches = self.search.visible_matches(-display_offset, self.size.1 as i32 - 1 - display_offset);
+        
+        // グリッドの各セルを描画
+        for indexed in content.display_iter {
+            let cell = indexed.cell;
+            
+            // 全角文字の右半分は左側のセルでまとめて描画する
+            if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+                continue;
+            }
+            
+            let row = indexed.point.line.0 + display_offset;
+            let x = rect.min.x + indexed.point.column.0 as f32 * self.cell_size.0;
             let y = rect.min.y + row as f32 * self.cell_size.1;
             
-            // 行の内容を描画
-            if !line.is_empty() {
-                painter.text(
-                    Pos2::new(rect.min.x, y),
-                    Align2::LEFT_TOP,
-                    line,
-                    egui::FontId::monospace(self.cell_size.1 * 0.8),
-                    COLORS[7] // 白
-                );
+            let (mut fg, mut bg) = self.palette.cell_colors(
+                cell,
+                content.colors,
+                self.config.draw_bold_text_with_bright_colors,
+            );
+            let columns = if cell.flags.contains(Flags::WIDE_CHAR) { 2.0 } else { 1.0 };
+            let cell_rect = Rect::from_min_size(
+                Pos2::new(x, y),
+                Vec2::new(self.cell_size.0 * columns, self.cell_size.1),
+            );
+            
+            // 選択範囲は前景色と背景色を入れ替えて表示
+            let selected = selection.is_some_and(|range| range.contains_cell(&indexed, cursor.point, cursor.shape));
+            if selected {
+                std::mem::swap(&mut fg, &mut bg);
+            }
+            
+            // 検索の一致を強調表示（選択中の一致は別の色）
+            let search_match = search_matches.iter().find(|(range, _)| range.contains(&indexed.point));
+            if let Some((_, focused)) = search_match {
+                fg = search::MATCH_FOREGROUND;
+                bg = if *focused { search::FOCUSED_MATCH_BACKGROUND } else { search::MATCH_BACKGROUND };
+            }
+            
+            // 既定の背景色と異なる場合のみセルの背景を塗る
+            if bg != background || selected || search_match.is_some() {
+                painter.rect_filled(cell_rect, 0.0, bg);
+            }
+            
+            // 非表示の文字は装飾も含めて描画しない
+            if cell.flags.contains(Flags::HIDDEN) {
+                continue;
+            }
+            
+            // 結合文字などの幅0の文字は直前の文字と合わせて1つのグリフとして描画
+            let zerowidth = cell.zerowidth().unwrap_or_default();
+            if (cell.c != ' ' && cell.c != '\t') || !zerowidth.is_empty() {
+                let mut text = String::with_capacity(1 + zerowidth.len());
+                text.push(cell.c);
+                text.extend(zerowidth.iter().filter(|c| !is_invisible(**c)));
+                draw_glyph(painter, cell_rect, self.glyph_offset, &text, &font_id, fg, cell.flags);
+            }
+            
+            // 下線と取り消し線
+            let underline_color = cell
+                .underline_color()
+                .map(|color| self.palette.resolve(color, content.colors))
+                .unwrap_or(fg);
+            draw_underline(painter, cell_rect, cell.flags, underline_color);
+            
+            // ポインタ下のリンクには下線を引く
+            let hovered = self.hovered_link.as_ref().is_some_and(|link| link.range.contains(&indexed.point));
+            if hovered && !cell.flags.intersects(Flags::ALL_UNDERLINES) {
+                draw_underline(painter, cell_rect, Flags::UNDERLINE, fg);
+            }
+            if cell.flags.contains(Flags::STRIKEOUT) {
+                let y = cell_rect.center().y;
+                painter.hline(cell_rect.x_range(), y, Stroke::new(1.0, fg));
             }
         }
         
         // カーソルを描画
-        if self.cursor_pos.1 < self.size.1 as usize {
-            let cursor_x = rect.min.x + self.cursor_pos.0 as f32 * self.cell_size.0;
-            let cursor_y = rect.min.y + self.cursor_pos.1 as f32 * self.cell_size.1;
-            
-            let cursor_rect = Rect::from_min_size(
-                Pos2::new(cursor_x, cursor_y),
-                Vec2::new(self.cell_size.0, self.cell_size.1)
-            );
-            
-            painter.rect_filled(cursor_rect, 0.0, Color32::from_rgba_unmultiplied(200, 200, 200, 128));
+        let cursor_row = cursor.point.line.0 + display_offset;
+        let cursor_x = rect.min.x + cursor.point.column.0 as f32 * self.cell_size.0;
+        let cursor_y = rect.min.y + cursor_row as f32 * self.cell_size.1;
+        
+        // 全角文字の上では2セル分の幅にする
+        let columns = if term.grid()[cursor.point].flags.contains(Flags::WIDE_CHAR) { 2.0 } else { 1.0 };
+        let cursor_rect = Rect::from_min_size(
+            Pos2::new(cursor_x, cursor_y),
+            Vec2::new(self.cell_size.0 * columns, self.cell_size.1)
+        );
+        
+        if !self.preedit.is_empty() {
+            // 変換中はカーソルの代わりに未確定文字列を表示
+            let foreground = self.palette.resolve(Color::Named(NamedColor::Foreground), content.colors);
+            self.draw_preedit(painter, cursor_rect.min, &font_id, foreground, background);
+        } else if cursor.shape != CursorShape::Hidden && cursor_row < self.size.1 as i32 {
+            // フォーカスがないときは点滅させず中抜きのブロックで表示
+            let focused = self.focused && response.has_focus();
+            let shape = if focused { cursor.shape } else { CursorShape::HollowBlock };
+            let blinking = focused && term.cursor_style().blinking;
+            if self.cursor_blink_visible(ui.ctx(), blinking) {
+                let cell = &term.grid()[cursor.point];
+                self.draw_cursor(painter, cursor_rect, shape, cell, content.colors, &font_id);
+            }
         }
         
+        // ヒントのラベル
+        self.draw_hints(painter, rect, &font_id, display_offset);
+        drop(term);
+        
+        // プロンプトの位置と終了状態のマーク
+        self.gutter_ui(ui, response.id.with("gutter"), gutter_rect);
+        
         // フォーカスがある場合はキーボード入力を処理
-        if self.focused && response.has_focus() {
-            let input = ui.input(|i| {
-                // 入力イベントを処理
-                let mut input_bytes = Vec::new();
+        if self.focused && response.has_focus() && self.pending_paste.is_none() && self.pending_clipboard.is_none() {
+            // Shift+PageUp/PageDown はシェルに送らずスクロールバックを移動する。
+            // 代替スクリーンにはスクロールバックがないため、アプリケーションに送る
+            let alt_screen = self.term.lock().mode().contains(TermMode::ALT_SCREEN);
+            if !alt_screen && ui.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::PageUp)) {
+                self.term.lock().scroll_display(Scroll::PageUp);
+            }
+            if !alt_screen && ui.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::PageDown)) {
+                self.term.lock().scroll_display(Scroll::PageDown);
+            }
+            
+            // Ctrl+Shift+F で検索バーを開く
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::F)) {
+                self.open_search();
+            }
+            
+            // ヒントモードを開始する（選んだヒントに対する操作ごとにキーが異なる）
+            let hint_keys = [
+                (egui::Key::O, HintAction::Open),
+                (egui::Key::Y, HintAction::Copy),
+                (egui::Key::P, HintAction::Paste),
+                (egui::Key::L, HintAction::SendToChat),
+            ];
+            for (key, action) in hint_keys {
+                if ui.input_mut(|i| i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, key)) {
+                    self.start_hints(action);
+                    self.preedit.clear();
+                }
+            }
+            
+            // Ctrl+Shift+Space でviモードを切り替える
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::Space)) {
+                self.toggle_vi_mode();
+                self.preedit.clear();
+            }
+            
+            // Ctrl+Shift+Up/Down で前後のプロンプトへ移動し、
+            // Ctrl+Shift+G で最後のコマンドの出力を選択する。
+            // シェル統合が有効でない場合や代替スクリーンではアプリケーションに送る
+            if !alt_screen && !self.commands.is_empty() {
+                if ui.input_mut(|i| i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::ArrowUp)) {
+                    self.jump_to_prompt(false);
+                }
+                if ui.input_mut(|i| i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::ArrowDown)) {
+                    self.jump_to_prompt(true);
+                }
+                if ui.input_mut(|i| i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::G)) {
+                    self.select_last_output();
+                }
+            }
+            
+            // Tab・矢印キー・Escでフォーカスが移動しないようにする
+            ui.memory_mut(|mem| {
+                mem.set_focus_lock_filter(
+                    response.id,
+                    egui::EventFilter {
+                        tab: true,
+                        horizontal_arrows: true,
+                        vertical_arrows: true,
+                        escape: true,
+                    },
+                )
+            });
+            
+            if self.hints.is_active() {
+                // ヒントモード中はラベルの入力を受け付ける
+                self.handle_hint_input(ui);
+            } else if self.term.lock().mode().contains(TermMode::VI) {
+                // viモード中のキー入力はシェルに送らずカーソル移動や選択に使う
+                self.handle_vi_input(ui);
+            } else {
+                // IMEの候補ウィンドウをカーソル位置に表示
+                ime::set_ime_output(ui, rect, cursor_rect);
                 
-                // キー入力を処理
-                for event in &i.events {
-                    if let egui::Event::Key {
-                        key, pressed: true, modifiers, ..
-                    } = event
-                    {
-                        // Ctrl修飾キーの処理
-                        if modifiers.ctrl {
-                            // Ctrlキーと組み合わせた文字キーの処理
-                            if let Some(c) = key.symbol_or_name().chars().next() {
-                                if c.is_ascii_alphabetic() {
-                                    // Ctrl+A～Zは1～26のコードに変換
-                                    let code = (c.to_ascii_uppercase() as u8 - b'A' + 1) as char;
-                                    input_bytes.push(code as u8);
-                                    continue;
+                let mode = *self.term.lock().mode();
+                let previous_preedit = self.preedit.clone();
+                let preedit = &mut self.preedit;
+                let (input, copy, paste) = ui.input(|i| {
+                    // 入力イベントを処理
+                    let mut input_bytes = Vec::new();
+                    let mut copy = false;
+                    let mut paste = None;
+                    
+                    // Ctrl+C/X/V などeguiがショートカットとして扱ったキーの変換
+                    let shortcut = |key| {
+                        keys::encode_kitty_key(key, None, i.modifiers, keys::KeyState::Pressed, None, mode)
+                            .or_else(|| keys::encode_key(key, i.modifiers, mode))
+                            .unwrap_or_default()
+                    };
+                    
+                    // キー入力と文字入力を発生順に処理
+                    let mut events = i.events.iter().peekable();
+                    while let Some(event) = events.next() {
+                        // Ctrl+C/X/V はeguiがCopy/Cut/Pasteイベントに変換する。
+                        // Shift併用時のみクリップボード操作とし、それ以外は制御文字を送る
+                        match event {
+                            egui::Event::Copy if i.modifiers.shift => copy = true,
+                            egui::Event::Copy => input_bytes.extend(shortcut(egui::Key::C)),
+                            egui::Event::Cut => input_bytes.extend(shortcut(egui::Key::X)),
+                            egui::Event::Paste(text) if i.modifiers.shift => paste = Some(text.clone()),
+                            egui::Event::Paste(_) => input_bytes.extend(shortcut(egui::Key::V)),
+                            // 変換中のキー入力はIMEが処理する
+                            egui::Event::Key { .. } if !preedit.is_empty() => {},
+                            egui::Event::Key { key, physical_key, pressed, repeat, modifiers } => {
+                                let text = match events.peek() {
+                                    Some(egui::Event::Text(text)) => Some(text.as_str()),
+                                    _ => None,
+                                };
+                                let state = keys::KeyState::new(*pressed, *repeat);
+                                if let Some(bytes) =
+                                    keys::encode_kitty_key(*key, *physical_key, *modifiers, state, text, mode)
+                                {
+                                    // 文字はシーケンスに含めたのでTextイベントは送らない
+                                    input_bytes.extend(bytes);
+                                    if *pressed && text.is_some() {
+                                        events.next();
+                                    }
+                                } else if *pressed {
+                                    if let Some(bytes) = keys::encode_key(*key, *modifiers, mode) {
+                                        input_bytes.extend(bytes);
+                                    }
                                 }
-                            }
-                        }
-                        
-                        // 基本的なキーマッピング
-                        let bytes = match key {
-                            egui::Key::Enter => b"\r".to_vec(),
-                            egui::Key::Escape => b"\x1b".to_vec(),
-                            egui::Key::Tab => b"\t".to_vec(),
-                            egui::Key::Backspace => b"\x7f".to_vec(),
-                            egui::Key::Delete => b"\x1b[3~".to_vec(),
-                            egui::Key::ArrowUp => b"\x1b[A".to_vec(),
-                            egui::Key::ArrowDown => b"\x1b[B".to_vec(),
-                            egui::Key::ArrowRight => b"\x1b[C".to_vec(),
-                            egui::Key::ArrowLeft => b"\x1b[D".to_vec(),
-                            egui::Key::Home => b"\x1b[H".to_vec(),
-                            egui::Key::End => b"\x1b[F".to_vec(),
-                            egui::Key::PageUp => b"\x1b[5~".to_vec(),
-                            egui::Key::PageDown => b"\x1b[6~".to_vec(),
-                            _ => Vec::new(),
-                        };
-                        
-                        if !bytes.is_empty() {
-                            input_bytes.extend_from_slice(&bytes);
+                            },
+                            egui::Event::Text(text) => input_bytes.extend_from_slice(text.as_bytes()),
+                            egui::Event::Ime(egui::ImeEvent::Preedit(text)) => *preedit = text.clone(),
+                            egui::Event::Ime(egui::ImeEvent::Commit(text)) => {
+                                preedit.clear();
+                                input_bytes.extend_from_slice(text.as_bytes());
+                            },
+                            egui::Event::Ime(egui::ImeEvent::Disabled) => preedit.clear(),
+                            _ => {}
                         }
                     }
+                    
+                    (input_bytes, copy, paste)
+                });
+                
+                // 未確定文字列が変わったら次のフレームで描画し直す
+                if self.preedit != previous_preedit {
+                    ui.ctx().request_repaint();
                 }
                 
-                // 通常の文字入力
-                for c in i.events.iter().filter_map(|e| {
-                    if let egui::Event::Text(text) = e {
-                        Some(text.as_str())
-                    } else {
-                        None
-                    }
-                }) {
-                    input_bytes.extend_from_slice(c.as_bytes());
+                // 入力があれば送信し、表示を最下部に戻す
+                if !input.is_empty() {
+                    // 入力中はカーソルの点滅を止める
+                    self.cursor_blink_start = Instant::now();
+                    self.term.lock().scroll_display(Scroll::Bottom);
+                    self.write_to_pty(input);
                 }
                 
-                input_bytes
-            });
-            
-            // 入力があれば送信
-            if !input.is_empty() {
-                let _ = self.event_tx.send(TerminalEvent::Input(input));
+                // Ctrl+Shift+C / Ctrl+Shift+V
+                if copy {
+                    self.copy_selection(ui.ctx());
+                }
+                if let Some(text) = paste {
+                    self.paste(&text);
+                }
             }
+        } else {
+            // フォーカスを失ったら変換中の文字列は破棄し、ヒントモードも終わる
+            self.preedit.clear();
+            self.hints.cancel();
+        }
+        
+        // 検索バー（閉じたらターミナルにフォーカスを戻す）
+        if self.search_ui(ui, rect, response.id.with("search")) {
+            ui.memory_mut(|mem| mem.request_focus(response.id));
         }
+        
+        // 貼り付けの確認ダイアログ
+        self.paste_confirmation_ui(ui.ctx(), response.id.with("paste_confirmation"));
+        
+        // OSC 52 によるクリップボード操作の確認ダイアログ
+        self.clipboard_confirmation_ui(ui.ctx(), response.id.with("clipboard_confirmation"));
     }
     
-    // 出力を処理してバッファに追加
-    fn process_output(&mut self) {
-        // 非ブロッキングで出力を読み取る
-        while let Ok(output) = self.output_rx.try_recv() {
-            // 制御シーケンスの基本的な処理（完全なANSIパーサーではありません）
-            for c in output.chars() {
-                match c {
-                    '\r' => {
-                        // キャリッジリターン: カーソルを行の先頭に移動
-                        self.cursor_pos.0 = 0;
-                    },
-                    '\n' => {
-                        // 改行: カーソルを次の行に移動
-                        self.cursor_pos.1 += 1;
-                        if self.cursor_pos.1 >= self.buffer.len() {
-                            // バッファの最後に達したら、新しい行を追加
-                            self.buffer.push(String::new());
-                            
-                            // バッファサイズが大きくなりすぎた場合は、古い行を削除
-                            if self.buffer.len() > self.size.1 as usize * 3 {
-                                let excess = self.buffer.len() - self.size.1 as usize * 2;
-                                self.buffer.drain(0..excess);
-                                self.cursor_pos.1 -= excess;
-                            }
-                        }
-                    },
-                    '\t' => {
-                        // タブ: 8文字分のスペース
-                        for _ in 0..8 {
-                            if self.cursor_pos.0 < self.size.0 as usize {
-                                if self.cursor_pos.1 >= self.buffer.len() {
-                                    self.buffer.push(String::new());
-                                }
-                                while self.buffer[self.cursor_pos.1].len() <= self.cursor_pos.0 {
-                                    self.buffer[self.cursor_pos.1].push(' ');
-                                }
-                                self.cursor_pos.0 += 1;
-                            }
-                        }
-                    },
-                    '\x08' => {
-                        // バックスペース: カーソルを1つ左に移動
-                        if self.cursor_pos.0 > 0 {
-                            self.cursor_pos.0 -= 1;
-                        }
-                    },
-                    _ => {
-                        // 通常の文字: バッファに追加
-                        if !c.is_control() {
-                            if self.cursor_pos.1 >= self.buffer.len() {
-                                self.buffer.push(String::new());
-                            }
-                            
-                            // 現在の行が短い場合は、カーソル位置までスペースで埋める
-                            let line = &mut self.buffer[self.cursor_pos.1];
-                            while line.len() < self.cursor_pos.0 {
-                                line.push(' ');
-                            }
-                            
-                            // 文字を追加（安全に行う）
-                            if self.cursor_pos.0 >= line.len() {
-                                // 新しい文字を追加
-                                line.push(c);
-                            } else {
-                                // 既存の文字列を分割して挿入
-                                let mut new_line = String::new();
-                                for (i, ch) in line.chars().enumerate() {
-                                    if i == self.cursor_pos.0 {
-                                        new_line.push(c);
-                                    }
-                                    new_line.push(ch);
-                                }
-                                *line = new_line;
-                            }
-                            
-                            self.cursor_pos.0 += 1;
-                            if self.cursor_pos.0 >= self.size.0 as usize {
-                                self.cursor_pos.0 = 0;
-                                self.cursor_pos.1 += 1;
-                                if self.cursor_pos.1 >= self.buffer.len() {
-                                    self.buffer.push(String::new());
-                                }
-                            }
-                        }
-                    }
+    // カーソルを形状に合わせて描画する
+    fn draw_cursor(
+        &self,
+        painter: &egui::Painter,
+        rect: Rect,
+        shape: CursorShape,
+        cell: &Cell,
+        colors: &Colors,
+        font_id: &egui::FontId,
+    ) {
+        let color = self.palette.resolve(Color::Named(NamedColor::Cursor), colors);
+        let thickness = (self.cell_size.1 / 10.0).round().max(1.0);
+        
+        match shape {
+            CursorShape::Block => {
+                // ブロックカーソルの下の文字は背景色で描き直す
+                painter.rect_filled(rect, 0.0, color);
+                let zerowidth = cell.zerowidth().unwrap_or_default();
+                if !cell.flags.contains(Flags::HIDDEN) && (cell.c != ' ' || !zerowidth.is_empty()) {
+                    let (_, text_color) =
+                        self.palette.cell_colors(cell, colors, self.config.draw_bold_text_with_bright_colors);
+                    let mut text = String::from(cell.c);
+                    text.extend(zerowidth.iter().filter(|c| !is_invisible(**c)));
+                    draw_glyph(painter, rect, self.glyph_offset, &text, font_id, text_color, cell.flags);
                 }
+            },
+            CursorShape::Underline => {
+                let underline = Rect::from_min_max(Pos2::new(rect.min.x, rect.max.y - thickness), rect.max);
+                painter.rect_filled(underline, 0.0, color);
+            },
+            CursorShape::Beam => {
+                let beam = Rect::from_min_max(rect.min, Pos2::new(rect.min.x + thickness, rect.max.y));
+                painter.rect_filled(beam, 0.0, color);
+            },
+            CursorShape::HollowBlock => {
+                painter.rect_stroke(rect, 0.0, Stroke::new(1.0, color), StrokeKind::Inside);
+            },
+            CursorShape::Hidden => {},
+        }
+    }
+    
+    // 点滅中のカーソルを表示するタイミングかどうか
+    //
+    // 点滅する場合は次に表示が切り替わる時刻に再描画を要求する。
+    fn cursor_blink_visible(&self, ctx: &egui::Context, blinking: bool) -> bool {
+        if !blinking {
+            return true;
+        }
+        
+        let interval = self.config.cursor.blink_interval_ms.max(1) as u128;
+        let elapsed = self.cursor_blink_start.elapsed().as_millis();
+        let remaining = interval - elapsed % interval;
+        ctx.request_repaint_after(Duration::from_millis(remaining as u64));
+        
+        (elapsed / interval).is_multiple_of(2)
+    }
+    
+    // マウスホイールの移動量を行数に変換してスクロールする
+    fn handle_scroll_wheel(&mut self, ui: &egui::Ui, rect: Rect, mouse_reporting: bool) {
+        let delta = ui.input(|i| i.smooth_scroll_delta.y);
+        if delta == 0.0 {
+            return;
+        }
+        
+        // 1行に満たない移動量は次のフレームに持ち越す
+        self.scroll_accumulator += delta / self.cell_size.1;
+        let lines = self.scroll_accumulator.trunc();
+        if lines == 0.0 {
+            return;
+        }
+        self.scroll_accumulator -= lines;
+        
+        // マウス報告モードではホイールをボタン4/5として送る
+        if mouse_reporting {
+            if let Some(pos) = ui.input(|i| i.pointer.hover_pos()) {
+                let modifiers = ui.input(|i| i.modifiers);
+                self.report_wheel(rect, pos, lines as i32, modifiers);
             }
+            return;
+        }
+        
+        let mut term = self.term.lock();
+        let mode = *term.mode();
+        if mode.contains(TermMode::ALT_SCREEN | TermMode::ALTERNATE_SCROLL) {
+            // 代替スクリーンにはスクロールバックがないため、
+            // less や man がスクロールできるようホイールを上下キーとして送る
+            let prefix = if mode.contains(TermMode::APP_CURSOR) { "\x1bO" } else { "\x1b[" };
+            let key = if lines > 0.0 { 'A' } else { 'B' };
+            let bytes = format!("{}{}", prefix, key).repeat(lines.abs() as usize);
+            drop(term);
+            self.write_to_pty(bytes.into_bytes());
+        } else {
+            term.scroll_display(Scroll::Delta(lines as i32));
         }
     }
     
-    // キーボード入力を処理するメソッド
-    pub fn handle_key_press(&mut self, input: &str) {
-        let _ = self.event_tx.send(TerminalEvent::Input(input.as_bytes().to_vec()));
+    // スクロールバックの位置を示すスクロールバー（ドラッグで移動可能）
+    fn scrollbar_ui(&mut self, ui: &egui::Ui, id: egui::Id, rect: Rect) {
+        let response = ui.interact(rect, id, egui::Sense::click_and_drag());
+        
+        let mut term = self.term.lock();
+        let history_size = term.grid().history_size();
+        if history_size == 0 {
+            return;
+        }
+        
+        let screen_lines = term.grid().screen_lines();
+        let total_lines = history_size + screen_lines;
+        let thumb_height = (rect.height() * screen_lines as f32 / total_lines as f32).max(SCROLLBAR_MIN_THUMB);
+        let track_height = rect.height() - thumb_height;
+        
+        // ドラッグまたはクリックされた位置へスクロール
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let position = ((pointer.y - rect.top() - thumb_height / 2.0) / track_height).clamp(0.0, 1.0);
+            let target = ((1.0 - position) * history_size as f32).round() as i32;
+            let current = term.grid().display_offset() as i32;
+            term.scroll_display(Scroll::Delta(target - current));
+        }
+        
+        // 表示位置（0.0 が最上部、1.0 が最下部）
+        let display_offset = term.grid().display_offset();
+        let position = 1.0 - display_offset as f32 / history_size as f32;
+        let thumb = Rect::from_min_size(
+            Pos2::new(rect.left() + 2.0, rect.top() + track_height * position),
+            Vec2::new(rect.width() - 4.0, thumb_height),
+        );
+        
+        let alpha = if response.hovered() || response.dragged() { 160 } else { 80 };
+        ui.painter().rect_filled(thumb, 3.0, Color32::from_rgba_unmultiplied(200, 200, 200, alpha));
+    }
+    
+    // グリッドとPTYのサイズを変更する
+    //
+    // PTYにはTIOCSWINSZで新しいサイズが設定され、シェルにSIGWINCHが送られる。
+    // グリッドは折り返された行を新しい幅で再配置する。
+    fn resize(&mut self, (cols, rows): (u16, u16)) {
+        self.size = (cols, rows);
+        
+        if let Some(notifier) = &mut self.notifier {
+            notifier.on_resize(WindowSize {
+                num_lines: rows,
+                num_cols: cols,
+                cell_width: self.cell_size.0 as u16,
+                cell_height: self.cell_size.1 as u16,
+            });
+        }
+        
+        let size = TermSize { columns: cols as usize, screen_lines: rows as usize };
+        self.term.lock().resize(size);
+    }
+    
+    // 設定を更新する（フォントが変わった場合は次のフレームでグリッドの大きさが変わる）
+    pub fn set_config(&mut self, config: TerminalConfig) {
+        self.term.lock().set_options(term_config(&config));
+        let patterns_changed = config.hint_patterns != self.config.hint_patterns;
+        self.config = config;
+        if patterns_changed {
+            self.update_hint_patterns();
+        }
+    }
+    
+    // 次に表示したときにキーボードフォーカスを移す
+    pub fn request_focus(&mut self) {
+        self.focus_requested = true;
+    }
+    
+    // キーボードフォーカスがあるかどうか
+    pub fn has_focus(&self, ctx: &egui::Context) -> bool {
+        self.widget_id.is_some_and(|id| ctx.memory(|mem| mem.has_focus(id)))
+    }
+    
+    // 端末エミュレータから届いたイベントを処理
+    //
+    // 表示していない端末も、応答の書き戻しやタイトルの更新のために毎フレーム呼ぶ。
+    pub fn process_events(&mut self, ctx: &egui::Context) {
+        while let Ok(event) = self.event_rx.try_recv() {
+            match event {
+                // デバイスステータス報告などの応答をPTYへ書き戻す
+                Event::PtyWrite(text) => self.write_to_pty(text.into_bytes()),
+                // シェルが終了したらイベントループも停止している
+                Event::Exit => {
+                    self.notifier = None;
+                    write_message(&self.term, "\n[プロセスが終了しました]\n");
+                },
+                // OSC 0/2 とタイトルスタック（CSI 22/23 t）によるタイトルの変更
+                Event::Title(title) => {
+                    let title: String = title.chars().filter(|c| !c.is_control()).collect();
+                    self.title = Some(title);
+                },
+                Event::ResetTitle => self.title = None,
+                // 新しい出力が届いたら検索の一致を探し直す
+                Event::Wakeup => self.search.mark_dirty(),
+                // OSC 52 によるクリップボードの書き込みと読み取り
+                Event::ClipboardStore(_, text) => {
+                    self.handle_clipboard_request(ctx, ClipboardRequest::Store(text));
+                },
+                Event::ClipboardLoad(_, format) => {
+                    self.handle_clipboard_request(ctx, ClipboardRequest::Load(format));
+                },
+                _ => {}
+            }
+        }
+    }
+    
+    // シェルの現在の作業ディレクトリ
+    pub fn working_directory(&self) -> Option<PathBuf> {
+        let pid = self.child_pid?;
+        if cfg!(target_os = "linux") {
+            std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()
+        } else {
+            None
+        }
+    }
+    
+    // シェルがフォアグラウンドで実行中のコマンドの名前
+    //
+    // 端末のフォアグラウンドプロセスグループがシェル自身でなければ、
+    // シェルから起動したコマンドが実行中と判断する。
+    pub fn foreground_job(&self) -> Option<String> {
+        let pid = self.child_pid?;
+        if !cfg!(target_os = "linux") || self.has_exited() {
+            return None;
+        }
+        
+        // コマンド名に空白や括弧が含まれる場合があるため、最後の ')' より後を読む
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let (_, fields) = stat.rsplit_once(')')?;
+        // state ppid pgrp session tty_nr tpgid ...
+        let tpgid: u32 = fields.split_whitespace().nth(5)?.parse().ok()?;
+        if tpgid == 0 || tpgid == pid {
+            return None;
+        }
+        
+        let name = std::fs::read_to_string(format!("/proc/{}/comm", tpgid)).unwrap_or_default();
+        Some(name.trim().to_string())
     }
-} 
\ No newline at end of file
+    
+    // シェルが終了したかどうか
+    pub fn has_exited(&self) -> bool {
+        self.notifier.is_none()
+    }
+    
+    // アプリケーションが設定した端末のタイトル
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref().filter(|title| !title.is_empty())
+    }
+    
+    // PTYへバイト列を書き込む
+    fn write_to_pty(&self, bytes: impl Into<Cow<'static, [u8]>>) {
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(bytes);
+        }
+    }
+}
+
+impl Drop for TerminalPane {
+    fn drop(&mut self) {
+        // イベントループを停止し、PTYを閉じてシェルにSIGHUPを送る
+        if let Some(notifier) = &self.notifier {
+            let _ = notifier.0.send(Msg::Shutdown);
+        }
+    }
+}
+
+// アプリケーションの設定から端末エミュレータの設定を作る
+fn term_config(config: &TerminalConfig) -> term::Config {
+    let shape = match config.cursor.shape {
+        config::CursorShape::Block => CursorShape::Block,
+        config::CursorShape::Underline => CursorShape::Underline,
+        config::CursorShape::Beam => CursorShape::Beam,
+    };
+    
+    term::Config {
+        scrolling_history: config.scrollback_lines,
+        default_cursor_style: CursorStyle { shape, blinking: config.cursor.blinking },
+        vi_mode_cursor_style: Some(CursorStyle { shape: CursorShape::Block, blinking: false }),
+        kitty_keyboard: true,
+        osc52: clipboard::osc52_mode(config),
+        ..Default::default()
+    }
+}
+
+// 1セル分のグリフを描画する
+//
+// 等幅フォントには太字・斜体の書体がないため、斜体はeguiのスキュー表示、
+// 太字は少しずらして重ね描きすることで表現する。グリフの幅がセルと
+// 異なる場合（全角文字のフォールバックなど）はセルの中央に揃える。
+fn draw_glyph(
+    painter: &egui::Painter,
+    rect: Rect,
+    glyph_offset: f32,
+    text: &str,
+    font_id: &egui::FontId,
+    color: Color32,
+    flags: Flags,
+) {
+    let format = TextFormat {
+        font_id: font_id.clone(),
+        color,
+        italics: flags.contains(Flags::ITALIC),
+        ..Default::default()
+    };
+    let galley = painter.layout_job(LayoutJob::single_section(text.to_owned(), format));
+    let x = rect.min.x + ((rect.width() - galley.size().x) / 2.0).max(0.0);
+    let pos = Pos2::new(x, rect.min.y + glyph_offset);
+    
+    if flags.contains(Flags::BOLD) {
+        painter.galley(pos + Vec2::new(BOLD_OFFSET, 0.0), galley.clone(), color);
+    }
+    painter.galley(pos, galley, color);
+}
+
+// ゼロ幅接合子や異体字セレクタなど、それ自体は表示されない文字
+//
+// フォントにグリフがないと代替文字の四角が描画されてしまうため除外する。
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{200b}'..='\u{200f}' | '\u{2060}'..='\u{2064}' | '\u{fe00}'..='\u{fe0f}')
+}
+
+// 下線（一重・二重・波線・点線・破線）を描画する
+fn draw_underline(painter: &egui::Painter, rect: Rect, flags: Flags, color: Color32) {
+    let stroke = Stroke::new(1.0, color);
+    let y = rect.bottom() - 1.5;
+    
+    if flags.contains(Flags::DOUBLE_UNDERLINE) {
+        painter.hline(rect.x_range(), y - 2.0, stroke);
+        painter.hline(rect.x_range(), y, stroke);
+    } else if flags.contains(Flags::UNDERCURL) {
+        // 半周期ごとに上下する波線
+        let amplitude = 1.0;
+        let steps = 8;
+        let points = (0..=steps)
+            .map(|i| {
+                let t = i as f32 / steps as f32;
+                let phase = t * std::f32::consts::TAU;
+                Pos2::new(rect.left() + t * rect.width(), y - 1.0 + phase.sin() * amplitude)
+            })
+            .collect();
+        painter.add(egui::Shape::line(points, stroke));
+    } else if flags.contains(Flags::DOTTED_UNDERLINE) {
+        draw_dashed_line(painter, rect, y, 1.0, stroke);
+    } else if flags.contains(Flags::DASHED_UNDERLINE) {
+        draw_dashed_line(painter, rect, y, 3.0, stroke);
+    } else if flags.contains(Flags::UNDERLINE) {
+        painter.hline(rect.x_range(), y, stroke);
+    }
+}
+
+// セル幅の中に一定間隔で線分を並べる
+fn draw_dashed_line(painter: &egui::Painter, rect: Rect, y: f32, dash: f32, stroke: Stroke) {
+    let mut x = rect.left();
+    while x < rect.right() {
+        let end = (x + dash).min(rect.right());
+        painter.hline(x..=end, y, stroke);
+        x += dash * 2.0;
+    }
+}
+
+// PTYを使わずに端末へメッセージを表示する
+fn write_message(term: &FairMutex<Term<EventProxy>>, message: &str) {
+    let mut term = term.lock();
+    let mut parser: ansi::Processor = ansi::Processor::new();
+    for byte in message.replace('\n', "\r\n").bytes() {
+        parser.advance(&mut *term, byte);
+    }
+}
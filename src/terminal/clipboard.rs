@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use egui::{Color32, Modal, RichText, ScrollArea};
+use alacritty_terminal::term::Osc52;
+
+use crate::config::{ClipboardPolicy, TerminalConfig};
+use super::TerminalPane;
+
+// 確認ダイアログでプレビューする最大文字数
+const PREVIEW_CHARS: usize = 1000;
+
+// OSC 52 によるクリップボード操作の要求
+//
+// eguiはプライマリセレクションを扱えないため、どちらの種類も
+// 通常のクリップボードに対する操作として扱う。
+pub(super) enum ClipboardRequest {
+    // クリップボードへの書き込み
+    Store(String),
+    // クリップボードの読み取り（内容を応答シーケンスに変換する関数を持つ）
+    Load(Arc<dyn Fn(&str) -> String + Sync + Send + 'static>),
+}
+
+impl TerminalPane {
+    // 設定に従ってクリップボード操作の要求を処理する
+    pub(super) fn handle_clipboard_request(&mut self, ctx: &egui::Context, request: ClipboardRequest) {
+        let policy = match request {
+            ClipboardRequest::Store(_) => self.config.osc52_copy,
+            ClipboardRequest::Load(_) => self.config.osc52_paste,
+        };
+        
+        match policy {
+            ClipboardPolicy::Allow => self.perform_clipboard_request(ctx, request),
+            ClipboardPolicy::Deny => {},
+            // 確認中に届いた要求は拒否する
+            ClipboardPolicy::Ask if self.pending_clipboard.is_some() => {},
+            ClipboardPolicy::Ask => self.pending_clipboard = Some(request),
+        }
+    }
+    
+    fn perform_clipboard_request(&self, ctx: &egui::Context, request: ClipboardRequest) {
+        match request {
+            ClipboardRequest::Store(text) => ctx.copy_text(text),
+            ClipboardRequest::Load(format) => {
+                // 読み取れなかった場合は応答しない
+                if let Some(text) = read_clipboard() {
+                    self.write_to_pty(format(&text).into_bytes());
+                }
+            },
+        }
+    }
+    
+    // クリップボード操作の確認ダイアログ
+    pub(super) fn clipboard_confirmation_ui(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(request) = &self.pending_clipboard else {
+            return;
+        };
+        
+        let mut allowed = false;
+        let mut denied = false;
+        
+        let modal = Modal::new(id).show(ctx, |ui| {
+            ui.set_max_width(500.0);
+            ui.heading("クリップボードへのアクセス");
+            
+            match request {
+                ClipboardRequest::Store(text) => {
+                    ui.label("端末内のプログラムがクリップボードへの書き込みを要求しています。");
+                    ui.add_space(8.0);
+                    
+                    let preview: String = text.chars().take(PREVIEW_CHARS).collect();
+                    ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        ui.label(RichText::new(preview).monospace());
+                        let count = text.chars().count();
+                        if count > PREVIEW_CHARS {
+                            ui.label(
+                                RichText::new(format!("…他 {} 文字", count - PREVIEW_CHARS))
+                                    .color(Color32::GRAY),
+                            );
+                        }
+                    });
+                },
+                ClipboardRequest::Load(_) => {
+                    ui.label("端末内のプログラムがクリップボードの内容の読み取りを要求しています。\n許可するとクリップボードの内容がプログラムに送られます。");
+                },
+            }
+            
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                allowed = ui.button("許可").clicked();
+                denied = ui.button("拒否").clicked();
+            });
+        });
+        
+        if modal.should_close() {
+            denied = true;
+        }
+        
+        if allowed {
+            if let Some(request) = self.pending_clipboard.take() {
+                self.perform_clipboard_request(ctx, request);
+            }
+        } else if denied {
+            self.pending_clipboard = None;
+        }
+    }
+}
+
+// 設定に対応する端末側の OSC 52 の受け付け範囲
+pub(super) fn osc52_mode(config: &TerminalConfig) -> Osc52 {
+    let copy = config.osc52_copy != ClipboardPolicy::Deny;
+    let paste = config.osc52_paste != ClipboardPolicy::Deny;
+    match (copy, paste) {
+        (true, true) => Osc52::CopyPaste,
+        (true, false) => Osc52::OnlyCopy,
+        (false, true) => Osc52::OnlyPaste,
+        (false, false) => Osc52::Disabled,
+    }
+}
+
+// システムのクリップボードからテキストを読み取る
+//
+// eguiにはクリップボードを読み取るAPIがないため、arboardを直接使う。
+fn read_clipboard() -> Option<String> {
+    arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()).ok()
+}
@@ -0,0 +1,184 @@
+use egui::Color32;
+use alacritty_terminal::term::cell::{Cell, Flags};
+use alacritty_terminal::term::color::{Colors, COUNT};
+use alacritty_terminal::vte::ansi::{Color, NamedColor, Rgb};
+
+// ターミナルの色設定
+const COLORS: [Color32; 16] = [
+    Color32::from_rgb(0, 0, 0),       // 黒 (背景色)
+    Color32::from_rgb(205, 0, 0),     // 赤
+    Color32::from_rgb(0, 205, 0),     // 緑
+    Color32::from_rgb(205, 205, 0),   // 黄
+    Color32::from_rgb(0, 0, 238),     // 青
+    Color32::from_rgb(205, 0, 205),   // マゼンタ
+    Color32::from_rgb(0, 205, 205),   // シアン
+    Color32::from_rgb(229, 229, 229), // 白
+    Color32::from_rgb(127, 127, 127), // 明るい黒
+    Color32::from_rgb(255, 0, 0),     // 明るい赤
+    Color32::from_rgb(0, 255, 0),     // 明るい緑
+    Color32::from_rgb(255, 255, 0),   // 明るい黄
+    Color32::from_rgb(92, 92, 255),   // 明るい青
+    Color32::from_rgb(255, 0, 255),   // 明るいマゼンタ
+    Color32::from_rgb(0, 255, 255),   // 明るいシアン
+    Color32::from_rgb(255, 255, 255), // 明るい白
+];
+
+// 暗い色（SGR 2）を作るときの明るさの係数
+const DIM_FACTOR: f32 = 0.66;
+
+// xterm互換の256色パレットと前景色・背景色などの特殊色
+//
+// インデックスの割り当ては alacritty_terminal の `Colors` と同じ:
+// 0..16 が基本16色、16..232 がカラーキューブ、232..256 がグレースケール、
+// 256以降が NamedColor::Foreground などの特殊色。
+pub struct Palette {
+    colors: [Color32; COUNT],
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        let mut colors = [Color32::BLACK; COUNT];
+        
+        // 基本16色
+        colors[..16].copy_from_slice(&COLORS);
+        
+        // 6x6x6 のカラーキューブ
+        for r in 0..6 {
+            for g in 0..6 {
+                for b in 0..6 {
+                    let index = 16 + r * 36 + g * 6 + b;
+                    colors[index] = Color32::from_rgb(cube_level(r), cube_level(g), cube_level(b));
+                }
+            }
+        }
+        
+        // 24段階のグレースケール
+        for i in 0..24 {
+            let level = 8 + i as u8 * 10;
+            colors[232 + i] = Color32::from_rgb(level, level, level);
+        }
+        
+        // 特殊色は基本16色から導出する
+        colors[NamedColor::Foreground as usize] = COLORS[7];
+        colors[NamedColor::Background as usize] = COLORS[0];
+        colors[NamedColor::Cursor as usize] = COLORS[7];
+        colors[NamedColor::BrightForeground as usize] = COLORS[15];
+        colors[NamedColor::DimForeground as usize] = dim(COLORS[7]);
+        for i in 0..8 {
+            colors[NamedColor::DimBlack as usize + i] = dim(COLORS[i]);
+        }
+        
+        Self { colors }
+    }
+    
+    // セルの色を実際の表示色に変換する
+    //
+    // OSC 4/10/11 などでアプリケーションが上書きした色があればそちらを優先する。
+    pub fn resolve(&self, color: Color, overrides: &Colors) -> Color32 {
+        match color {
+            Color::Spec(rgb) => rgb_to_color32(rgb),
+            Color::Named(name) => self.indexed(name as usize, overrides),
+            Color::Indexed(index) => self.indexed(index as usize, overrides),
+        }
+    }
+    
+    // 属性（太字・暗い色・反転・非表示）を反映したセルの前景色と背景色
+    pub fn cell_colors(&self, cell: &Cell, overrides: &Colors, bold_is_bright: bool) -> (Color32, Color32) {
+        let flags = cell.flags;
+        let dim_bold = flags & Flags::DIM_BOLD;
+        
+        let mut fg = match cell.fg {
+            Color::Spec(rgb) if flags.contains(Flags::DIM) => dim(rgb_to_color32(rgb)),
+            Color::Spec(rgb) => rgb_to_color32(rgb),
+            Color::Named(name) => {
+                let name = match dim_bold {
+                    Flags::DIM_BOLD if name == NamedColor::Foreground => NamedColor::DimForeground,
+                    Flags::BOLD if bold_is_bright => name.to_bright(),
+                    Flags::DIM | Flags::DIM_BOLD => name.to_dim(),
+                    _ => name,
+                };
+                self.indexed(name as usize, overrides)
+            },
+            Color::Indexed(index) => {
+                let slot = match (dim_bold, index) {
+                    (Flags::BOLD, 0..=7) if bold_is_bright => index as usize + 8,
+                    (Flags::DIM | Flags::DIM_BOLD, 0..=7) => NamedColor::DimBlack as usize + index as usize,
+                    _ => index as usize,
+                };
+                let color = self.indexed(slot, overrides);
+                // 0..8 は暗い色のスロットに置き換え済みなので、それ以外だけ暗くする
+                if flags.contains(Flags::DIM) && index > 7 {
+                    dim(color)
+                } else {
+                    color
+                }
+            },
+        };
+        let mut bg = self.resolve(cell.bg, overrides);
+        
+        // 反転表示
+        if flags.contains(Flags::INVERSE) {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        
+        // 非表示の文字は背景色で描く
+        if flags.contains(Flags::HIDDEN) {
+            fg = bg;
+        }
+        
+        (fg, bg)
+    }
+    
+    fn indexed(&self, index: usize, overrides: &Colors) -> Color32 {
+        match overrides[index] {
+            Some(rgb) => rgb_to_color32(rgb),
+            None => self.colors[index],
+        }
+    }
+}
+
+// カラーキューブの各段階の明るさ（xtermと同じ値）
+fn cube_level(step: usize) -> u8 {
+    if step == 0 {
+        0
+    } else {
+        (55 + step * 40) as u8
+    }
+}
+
+fn dim(color: Color32) -> Color32 {
+    Color32::from_rgb(
+        (color.r() as f32 * DIM_FACTOR) as u8,
+        (color.g() as f32 * DIM_FACTOR) as u8,
+        (color.b() as f32 * DIM_FACTOR) as u8,
+    )
+}
+
+fn rgb_to_color32(rgb: Rgb) -> Color32 {
+    Color32::from_rgb(rgb.r, rgb.g, rgb.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn dim_cell(fg: Color) -> Cell {
+        Cell { fg, flags: Flags::DIM, ..Cell::default() }
+    }
+    
+    #[test]
+    fn dim_indexed_matches_named() {
+        let palette = Palette::new();
+        let overrides = Colors::default();
+        
+        // SGR 2;38;5;1 と SGR 2;31 は同じ暗い赤になる
+        let (indexed, _) = palette.cell_colors(&dim_cell(Color::Indexed(1)), &overrides, false);
+        let (named, _) = palette.cell_colors(&dim_cell(Color::Named(NamedColor::Red)), &overrides, false);
+        assert_eq!(indexed, named);
+        assert_eq!(indexed, dim(COLORS[1]));
+        
+        // 16以降のインデックスはその場で暗くする
+        let (cube, _) = palette.cell_colors(&dim_cell(Color::Indexed(196)), &overrides, false);
+        assert_eq!(cube, dim(palette.colors[196]));
+    }
+}
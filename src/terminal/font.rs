@@ -0,0 +1,73 @@
+use egui::{FontData, FontDefinitions, FontFamily, FontId};
+use std::{fs, io, sync::Arc};
+
+use crate::config::FontConfig;
+
+// ターミナル用に登録するフォントファミリー名
+const TERMINAL_FAMILY: &str = "terminal";
+
+// 設定ファイルで指定されたフォントの登録名
+const CUSTOM_FONT_NAME: &str = "terminal-custom";
+
+// 設定されたフォントを "terminal" ファミリーとしてeguiに登録する
+//
+// 指定されたフォントにないグリフは組み込みの等幅フォントで補う。
+// 読み込みに失敗した場合も組み込みフォントだけで登録した上でエラーを返す。
+pub fn install(ctx: &egui::Context, config: &FontConfig) -> io::Result<()> {
+    let mut fonts = FontDefinitions::default();
+    let mut family = fonts.families.get(&FontFamily::Monospace).cloned().unwrap_or_default();
+    
+    let result = match &config.path {
+        Some(path) => load_font_file(path).map(|data| {
+            fonts.font_data.insert(CUSTOM_FONT_NAME.to_string(), Arc::new(data));
+            family.insert(0, CUSTOM_FONT_NAME.to_string());
+        }),
+        None => Ok(()),
+    };
+    
+    fonts.families.insert(FontFamily::Name(TERMINAL_FAMILY.into()), family);
+    ctx.set_fonts(fonts);
+    result
+}
+
+// ターミナルの描画に使うフォント
+pub fn font_id(ui: &egui::Ui, config: &FontConfig) -> FontId {
+    let family = FontFamily::Name(TERMINAL_FAMILY.into());
+    
+    // set_fonts は次のフレームから有効になるため、登録前は組み込みの等幅フォントを使う
+    if ui.fonts(|fonts| fonts.families().contains(&family)) {
+        FontId::new(config.size, family)
+    } else {
+        FontId::new(config.size, FontFamily::Monospace)
+    }
+}
+
+// フォントメトリクスからセルの大きさを計算する
+//
+// 戻り値は (セル幅, セル高さ, セル内でのグリフの縦方向のオフセット)。
+pub fn cell_metrics(ui: &egui::Ui, font_id: &FontId, line_spacing: f32) -> (f32, f32, f32) {
+    ui.fonts(|fonts| {
+        let width = fonts.glyph_width(font_id, 'M');
+        let row_height = fonts.row_height(font_id);
+        let height = (row_height * line_spacing).max(row_height);
+        (width, height, (height - row_height) / 2.0)
+    })
+}
+
+fn load_font_file(path: &std::path::Path) -> io::Result<FontData> {
+    let bytes = fs::read(path)?;
+    
+    // eguiは不正なフォントデータでパニックするため、事前にヘッダーを確認する
+    let is_font = matches!(
+        bytes.get(..4),
+        Some([0x00, 0x01, 0x00, 0x00]) | Some(b"OTTO") | Some(b"true") | Some(b"ttcf")
+    );
+    if !is_font {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "TrueType/OpenTypeフォントではありません",
+        ));
+    }
+    
+    Ok(FontData::from_owned(bytes))
+}
@@ -0,0 +1,422 @@
+use egui::{Align2, Color32, Event, FontId, Key, Pos2, Rect, Vec2};
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::index::{Column, Direction, Line, Point};
+use alacritty_terminal::term::Term;
+use alacritty_terminal::term::cell::Hyperlink;
+use alacritty_terminal::term::search::{Match, RegexIter, RegexSearch};
+
+use super::links::{self, FileLocation, LinkAction, LinkTarget};
+use super::{shell_integration, TerminalPane};
+
+// ラベルに使う文字（ホームポジションに近い順）
+const HINT_ALPHABET: &str = "jfkdlsahgurieowpq";
+
+// 行番号付きでない、ディレクトリを含むファイルパス
+const DIRECTORY_PATH_REGEX: &str = r"(?:~|\.{1,2})?/?(?:[\w.\-]+/)+[\w.\-]+(?::[0-9]+(?::[0-9]+)?)?";
+
+// gitのコミットハッシュ（短縮形を含む）
+//
+// 大文字の単語や数字だけの並びと区別するため、大文字小文字を区別し、
+// 英字を少なくとも1つ含むものに限る。長さ（7～40文字）は一致した後で確かめる。
+const SHA_REGEX: &str = r"(?-i)(?-u:\b)[0-9]*[a-f][0-9a-f]*(?-u:\b)";
+const SHA_LENGTH: std::ops::RangeInclusive<usize> = 7..=40;
+
+// IPv4アドレス（ポート番号付きを含む）
+const IPV4_REGEX: &str = r"(?-u:\b)(?:[0-9]{1,3}\.){3}[0-9]{1,3}(?::[0-9]+)?(?-u:\b)";
+
+// ラベルの配色（Alacrittyの既定値と同じ）
+const HINT_FOREGROUND: Color32 = Color32::from_rgb(0x18, 0x18, 0x18);
+const HINT_BACKGROUND: Color32 = Color32::from_rgb(0xf4, 0xbf, 0x75);
+const HINT_TYPED_BACKGROUND: Color32 = Color32::from_rgb(0xac, 0x42, 0x42);
+
+// ラベルを選んだときの操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum HintAction {
+    // URLはブラウザで、ファイルはエディタで開く（それ以外はコピーする）
+    Open,
+    // クリップボードにコピーする
+    Copy,
+    // シェルのプロンプトに貼り付ける
+    Paste,
+    // チャットパネルの入力欄に送る
+    SendToChat,
+}
+
+impl HintAction {
+    // 画面に表示する名前
+    fn label(self) -> &'static str {
+        match self {
+            HintAction::Open => "開く",
+            HintAction::Copy => "コピー",
+            HintAction::Paste => "貼り付け",
+            HintAction::SendToChat => "チャットに送る",
+        }
+    }
+}
+
+// 一致したテキストの種類
+#[derive(Debug, Clone, Copy)]
+enum HintKind {
+    Url,
+    Path,
+    Sha,
+    Text,
+}
+
+// ヒントとして検出するパターン
+struct HintPattern {
+    regex: RegexSearch,
+    kind: HintKind,
+}
+
+// 表示中のヒント
+#[derive(Debug, Clone)]
+struct Hint {
+    range: Match,
+    text: String,
+    target: Option<LinkTarget>,
+    label: String,
+}
+
+// ヒントモードの状態
+pub(super) struct HintState {
+    action: Option<HintAction>, // ヒントモード中の操作
+    typed: String, // 入力済みのラベルの文字
+    hints: Vec<Hint>,
+    patterns: Vec<HintPattern>, // 優先度の高い順
+}
+
+impl HintState {
+    // 組み込みのパターンと設定された追加のパターンで作成する
+    //
+    // 追加のパターンのうち正規表現として不正なものは無視する。
+    pub(super) fn new(custom_patterns: &[String]) -> Self {
+        let builtin = [
+            (links::URL_REGEX, HintKind::Url),
+            (links::PATH_REGEX, HintKind::Path),
+            (DIRECTORY_PATH_REGEX, HintKind::Path),
+            (SHA_REGEX, HintKind::Sha),
+            (IPV4_REGEX, HintKind::Text),
+        ];
+        let custom = custom_patterns.iter().map(|pattern| (pattern.as_str(), HintKind::Text));
+        
+        let patterns = builtin
+            .into_iter()
+            .chain(custom)
+            .filter_map(|(pattern, kind)| Some(HintPattern { regex: RegexSearch::new(pattern).ok()?, kind }))
+            .collect();
+        
+        Self {
+            action: None,
+            typed: String::new(),
+            hints: Vec::new(),
+            patterns,
+        }
+    }
+    
+    // ヒントモード中かどうか
+    pub(super) fn is_active(&self) -> bool {
+        self.action.is_some()
+    }
+    
+    // ヒントモードを終わる
+    pub(super) fn cancel(&mut self) {
+        self.action = None;
+    }
+    
+    // 表示範囲のテキストからヒントを探し、ラベルを付け直す
+    fn update<T>(&mut self, term: &Term<T>) {
+        let display_offset = term.grid().display_offset() as i32;
+        let start = Point::new(Line(-display_offset), Column(0));
+        let end = Point::new(Line(term.screen_lines() as i32 - 1 - display_offset), term.last_column());
+        
+        // OSC 8 のハイパーリンクを優先し、重なる一致は優先度の高いものだけ残す
+        let mut hints = visible_hyperlinks(term, start, end);
+        for pattern in &mut self.patterns {
+            for range in RegexIter::new(start, end, Direction::Right, term, &mut pattern.regex) {
+                let range = match pattern.kind {
+                    HintKind::Url => links::trim_url(term, range),
+                    _ => range,
+                };
+                let overlaps = hints
+                    .iter()
+                    .any(|hint| hint.range.start() <= range.end() && range.start() <= hint.range.end());
+                if overlaps {
+                    continue;
+                }
+                
+                let text = term.bounds_to_string(*range.start(), *range.end());
+                if matches!(pattern.kind, HintKind::Sha) && !SHA_LENGTH.contains(&text.len()) {
+                    continue;
+                }
+                let target = match pattern.kind {
+                    HintKind::Url => Some(LinkTarget::Url(text.clone())),
+                    HintKind::Path => Some(LinkTarget::File(FileLocation::parse(&text))),
+                    HintKind::Sha | HintKind::Text => None,
+                };
+                hints.push(Hint { range, text, target, label: String::new() });
+            }
+        }
+        
+        hints.sort_by_key(|hint| *hint.range.start());
+        let labels = labels(hints.len());
+        for (hint, label) in hints.iter_mut().zip(labels) {
+            hint.label = label;
+        }
+        self.hints = hints;
+    }
+}
+
+impl TerminalPane {
+    // ヒントモードを開始する
+    pub(super) fn start_hints(&mut self, action: HintAction) {
+        self.hints.action = Some(action);
+        self.hints.typed.clear();
+    }
+    
+    // 設定された追加のパターンを反映する
+    pub(super) fn update_hint_patterns(&mut self) {
+        let action = self.hints.action;
+        self.hints = HintState::new(&self.config.hint_patterns);
+        self.hints.action = action;
+    }
+    
+    // ヒントモード中は毎フレーム表示中のテキストからヒントを探し直す
+    pub(super) fn update_hints(&mut self) {
+        if self.hints.is_active() {
+            let term = self.term.lock();
+            self.hints.update(&term);
+        }
+    }
+    
+    // ヒントモードのキー入力を処理する
+    //
+    // ラベルを最後まで入力すると操作を実行してヒントモードを終わる。
+    // Escで取り消し、Backspaceで入力したラベルの文字を消す。
+    pub(super) fn handle_hint_input(&mut self, ui: &egui::Ui) {
+        let events = ui.input(|i| i.events.clone());
+        for event in events {
+            match event {
+                Event::Key { key: Key::Escape, pressed: true, .. } => self.hints.cancel(),
+                Event::Key { key: Key::Backspace, pressed: true, .. } => {
+                    self.hints.typed.pop();
+                },
+                Event::Text(text) => {
+                    for c in text.chars() {
+                        self.type_hint_label(ui.ctx(), c);
+                    }
+                },
+                _ => {},
+            }
+            if !self.hints.is_active() {
+                break;
+            }
+        }
+    }
+    
+    // ラベルの文字を1文字入力する
+    fn type_hint_label(&mut self, ctx: &egui::Context, c: char) {
+        let Some(action) = self.hints.action else {
+            return;
+        };
+        
+        let mut typed = self.hints.typed.clone();
+        typed.push(c.to_ascii_lowercase());
+        
+        // どのラベルにも当てはまらない文字は無視する
+        if !self.hints.hints.iter().any(|hint| hint.label.starts_with(&typed)) {
+            return;
+        }
+        self.hints.typed = typed;
+        
+        let selected = self.hints.hints.iter().find(|hint| hint.label == self.hints.typed).cloned();
+        if let Some(hint) = selected {
+            self.hints.cancel();
+            self.run_hint_action(ctx, action, hint);
+        }
+    }
+    
+    fn run_hint_action(&mut self, ctx: &egui::Context, action: HintAction, hint: Hint) {
+        // 相対パスはシェルの作業ディレクトリを基準にする
+        let cwd = self.working_directory();
+        let target = hint.target.map(|target| match target {
+            LinkTarget::File(mut location) => {
+                location.path = links::resolve_path(&location.path, cwd.as_deref());
+                LinkTarget::File(location)
+            },
+            target => target,
+        });
+        
+        match action {
+            HintAction::Open => match &target {
+                Some(target) => self.open_link(ctx, target),
+                None => ctx.copy_text(hint.text),
+            },
+            HintAction::Copy => ctx.copy_text(hint.text),
+            HintAction::Paste => self.paste(&hint.text),
+            HintAction::SendToChat => {
+                let text = match &target {
+                    Some(LinkTarget::File(location)) => location.chat_context(),
+                    _ => hint.text,
+                };
+                self.link_actions.push(LinkAction::SendToChat(text));
+            },
+        }
+    }
+    
+    // ヒントのラベルと、現在の操作の案内を描画する
+    pub(super) fn draw_hints(&self, painter: &egui::Painter, rect: Rect, font_id: &FontId, display_offset: i32) {
+        let Some(action) = self.hints.action else {
+            return;
+        };
+        
+        let (cell_width, cell_height) = self.cell_size;
+        for hint in &self.hints.hints {
+            let Some(rest) = hint.label.strip_prefix(self.hints.typed.as_str()) else {
+                continue;
+            };
+            
+            // 入力済みの文字は色を変えて残し、ラベルの位置がずれないようにする
+            let start = hint.range.start();
+            let row = start.line.0 + display_offset;
+            let typed_chars = self.hints.typed.chars().map(|c| (c, HINT_TYPED_BACKGROUND));
+            let rest_chars = rest.chars().map(|c| (c, HINT_BACKGROUND));
+            for (i, (c, background)) in typed_chars.chain(rest_chars).enumerate() {
+                let column = start.column.0 + i;
+                if column >= self.size.0 as usize {
+                    break;
+                }
+                
+                let min = rect.min + Vec2::new(column as f32 * cell_width, row as f32 * cell_height);
+                let cell_rect = Rect::from_min_size(min, Vec2::new(cell_width, cell_height));
+                painter.rect_filled(cell_rect, 0.0, background);
+                painter.text(
+                    Pos2::new(cell_rect.center().x, cell_rect.min.y + self.glyph_offset),
+                    Align2::CENTER_TOP,
+                    c,
+                    font_id.clone(),
+                    HINT_FOREGROUND,
+                );
+            }
+        }
+        
+        // 右下に操作の案内を表示する
+        let message = format!("ヒント（{}）: ラベルを入力　Esc: 取り消し", action.label());
+        let galley = painter.layout_no_wrap(message, FontId::proportional(12.0), HINT_FOREGROUND);
+        let banner = Rect::from_min_size(
+            rect.right_bottom() - galley.size() - Vec2::new(16.0, 12.0),
+            galley.size() + Vec2::new(8.0, 4.0),
+        );
+        painter.rect_filled(banner, 4.0, HINT_BACKGROUND);
+        painter.galley(banner.min + Vec2::new(4.0, 2.0), galley, HINT_FOREGROUND);
+    }
+}
+
+// 表示範囲にある OSC 8 のハイパーリンク
+fn visible_hyperlinks<T>(term: &Term<T>, start: Point, end: Point) -> Vec<Hint> {
+    let mut hints = Vec::new();
+    let mut current: Option<(Hyperlink, Match)> = None;
+    
+    let mut push = |current: Option<(Hyperlink, Match)>| {
+        if let Some((hyperlink, range)) = current {
+            let text = hyperlink.uri().to_string();
+            hints.push(Hint { range, text, target: Some(links::hyperlink_target(&hyperlink)), label: String::new() });
+        }
+    };
+    
+    let mut point = start;
+    while point <= end {
+        let hyperlink = term.grid()[point].hyperlink().filter(|hyperlink| !shell_integration::is_mark(hyperlink));
+        match (&mut current, hyperlink) {
+            (Some((current, range)), Some(hyperlink)) if *current == hyperlink => *range = *range.start()..=point,
+            (_, hyperlink) => {
+                push(current.take());
+                current = hyperlink.map(|hyperlink| (hyperlink, point..=point));
+            },
+        }
+        
+        point = if point.column < term.last_column() {
+            Point::new(point.line, point.column + 1)
+        } else {
+            Point::new(point.line + 1, Column(0))
+        };
+    }
+    push(current);
+    
+    hints
+}
+
+// `count` 個のラベルを作る
+//
+// どのラベルも他のラベルの先頭部分にならないよう、すべて同じ長さにする。
+fn labels(count: usize) -> Vec<String> {
+    let alphabet: Vec<char> = HINT_ALPHABET.chars().collect();
+    
+    let mut length = 1;
+    while alphabet.len().pow(length) < count {
+        length += 1;
+    }
+    
+    (0..count)
+        .map(|mut index| {
+            let mut label = vec![alphabet[0]; length as usize];
+            for slot in label.iter_mut().rev() {
+                *slot = alphabet[index % alphabet.len()];
+                index /= alphabet.len();
+            }
+            label.into_iter().collect()
+        })
+        .collect()
+}
+
+// ヒントモードの追加のパターンとして使える正規表現かどうかを調べる
+pub fn check_hint_pattern(pattern: &str) -> Result<(), String> {
+    RegexSearch::new(pattern).map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alacritty_terminal::event::VoidListener;
+    use alacritty_terminal::term::Config;
+    use alacritty_terminal::term::test::TermSize;
+    use alacritty_terminal::vte::ansi::Processor;
+    
+    // 1行の端末に表示したテキストから見つかるヒントのテキスト
+    fn hint_texts(text: &str) -> Vec<String> {
+        let mut term = Term::new(Config::default(), &TermSize::new(80, 1), VoidListener);
+        let mut processor: Processor = Processor::new();
+        for &byte in text.as_bytes() {
+            processor.advance(&mut term, byte);
+        }
+        
+        let mut hints = HintState::new(&[]);
+        hints.update(&term);
+        hints.hints.into_iter().map(|hint| hint.text).collect()
+    }
+    
+    #[test]
+    fn commit_hashes() {
+        assert_eq!(hint_texts("commit 3f2a9c1 and deadbeefcafe"), ["3f2a9c1", "deadbeefcafe"]);
+        // 大文字の単語、数字だけの並び、短すぎるものは除く
+        assert!(hint_texts("DEFACED 1234567 abc123").is_empty());
+        assert!(hint_texts(&"a".repeat(41)).is_empty());
+    }
+    
+    #[test]
+    fn labels_have_equal_length() {
+        assert_eq!(labels(0), Vec::<String>::new());
+        assert_eq!(labels(3), ["j", "f", "k"]);
+        
+        let alphabet = HINT_ALPHABET.chars().count();
+        let two = labels(alphabet + 1);
+        assert!(two.iter().all(|label| label.chars().count() == 2));
+        assert_eq!(two[0], "jj");
+        assert_eq!(two[alphabet], "fj");
+        // どのラベルも重複しない
+        let mut unique = two.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), two.len());
+    }
+}
@@ -0,0 +1,54 @@
+use egui::{Color32, Pos2, Rect, Stroke, Vec2};
+use egui::output::IMEOutput;
+use alacritty_terminal::term::cell::Flags;
+use unicode_width::UnicodeWidthChar;
+
+use super::{draw_glyph, draw_underline, TerminalPane};
+
+impl TerminalPane {
+    // IMEで変換中の文字列をカーソル位置に下線付きで描画する
+    pub(super) fn draw_preedit(
+        &self,
+        painter: &egui::Painter,
+        origin: Pos2,
+        font_id: &egui::FontId,
+        fg: Color32,
+        bg: Color32,
+    ) {
+        let mut x = origin.x;
+        for c in self.preedit.chars() {
+            // 幅0の文字はセルを持たないので描画しない
+            let columns = c.width().unwrap_or(0);
+            if columns == 0 {
+                continue;
+            }
+            
+            let cell_rect = Rect::from_min_size(
+                Pos2::new(x, origin.y),
+                Vec2::new(self.cell_size.0 * columns as f32, self.cell_size.1),
+            );
+            painter.rect_filled(cell_rect, 0.0, bg);
+            draw_glyph(painter, cell_rect, self.glyph_offset, c.encode_utf8(&mut [0; 4]), font_id, fg, Flags::empty());
+            x = cell_rect.max.x;
+        }
+        
+        let preedit_rect = Rect::from_min_max(origin, Pos2::new(x, origin.y + self.cell_size.1));
+        draw_underline(painter, preedit_rect, Flags::UNDERLINE, fg);
+        
+        // 変換中の入力位置として末尾に細い縦線を表示
+        painter.vline(x, preedit_rect.y_range(), Stroke::new(1.0, fg));
+    }
+}
+
+// IMEの候補ウィンドウを端末のカーソル位置に表示させる
+//
+// 毎フレーム出力している間だけIMEが有効になる。
+pub(super) fn set_ime_output(ui: &egui::Ui, rect: Rect, cursor_rect: Rect) {
+    let to_global = ui.ctx().layer_transform_to_global(ui.layer_id()).unwrap_or_default();
+    ui.ctx().output_mut(|o| {
+        o.ime = Some(IMEOutput {
+            rect: to_global * rect,
+            cursor_rect: to_global * cursor_rect,
+        });
+    });
+}
@@ -0,0 +1,455 @@
+use egui::{Key, Modifiers};
+use alacritty_terminal::term::TermMode;
+
+// キー入力をxterm互換のバイト列に変換する
+//
+// 文字を入力するキーは続く Text イベントで送られるため、ここでは
+// Alt修飾の ESC プレフィックスだけを返す。変換できないキーは None。
+// テンキーはeguiでは通常のキーと区別できないため、DECKPAM
+// （アプリケーションキーパッドモード）は送信内容に影響しない。
+pub(super) fn encode_key(key: Key, modifiers: Modifiers, mode: TermMode) -> Option<Vec<u8>> {
+    let param = modifier_param(modifiers);
+    let alt_prefix = |bytes: &[u8]| {
+        let mut result = Vec::with_capacity(bytes.len() + 1);
+        if modifiers.alt {
+            result.push(0x1b);
+        }
+        result.extend_from_slice(bytes);
+        result
+    };
+    
+    let bytes = match key {
+        // カーソルキーとHome/End（DECCKMでアプリケーションモードに切り替わる）
+        Key::ArrowUp => cursor_key(b'A', param, mode),
+        Key::ArrowDown => cursor_key(b'B', param, mode),
+        Key::ArrowRight => cursor_key(b'C', param, mode),
+        Key::ArrowLeft => cursor_key(b'D', param, mode),
+        Key::Home => cursor_key(b'H', param, mode),
+        Key::End => cursor_key(b'F', param, mode),
+        
+        // 編集キーとF5以降のファンクションキー
+        Key::Insert => tilde_key(2, param),
+        Key::Delete => tilde_key(3, param),
+        Key::PageUp => tilde_key(5, param),
+        Key::PageDown => tilde_key(6, param),
+        Key::F5 => tilde_key(15, param),
+        Key::F6 => tilde_key(17, param),
+        Key::F7 => tilde_key(18, param),
+        Key::F8 => tilde_key(19, param),
+        Key::F9 => tilde_key(20, param),
+        Key::F10 => tilde_key(21, param),
+        Key::F11 => tilde_key(23, param),
+        Key::F12 => tilde_key(24, param),
+        
+        // F1～F4 は SS3 形式
+        Key::F1 => ss3_key(b'P', param),
+        Key::F2 => ss3_key(b'Q', param),
+        Key::F3 => ss3_key(b'R', param),
+        Key::F4 => ss3_key(b'S', param),
+        
+        Key::Enter if mode.contains(TermMode::LINE_FEED_NEW_LINE) => alt_prefix(b"\r\n"),
+        Key::Enter => alt_prefix(b"\r"),
+        Key::Tab if modifiers.shift => b"\x1b[Z".to_vec(),
+        Key::Tab => alt_prefix(b"\t"),
+        Key::Backspace if modifiers.ctrl => alt_prefix(b"\x08"),
+        Key::Backspace => alt_prefix(b"\x7f"),
+        Key::Escape => alt_prefix(b"\x1b"),
+        
+        // Ctrl+文字キーは制御文字に変換する
+        _ if modifiers.ctrl => alt_prefix(&[control_code(key)?]),
+        
+        // Alt+文字キーはESCを前置する（文字自体はTextイベントで届く）
+        _ if modifiers.alt && is_text_key(key) => b"\x1b".to_vec(),
+        
+        _ => return None,
+    };
+    
+    Some(bytes)
+}
+
+// 修飾キーのパラメータ（1 + Shift:1 + Alt:2 + Ctrl:4 + Meta:8）
+fn modifier_param(modifiers: Modifiers) -> u8 {
+    let mut param = 1;
+    if modifiers.shift {
+        param += 1;
+    }
+    if modifiers.alt {
+        param += 2;
+    }
+    if modifiers.ctrl {
+        param += 4;
+    }
+    if modifiers.mac_cmd {
+        param += 8;
+    }
+    param
+}
+
+fn cursor_key(c: u8, param: u8, mode: TermMode) -> Vec<u8> {
+    if param > 1 {
+        format!("\x1b[1;{}{}", param, c as char).into_bytes()
+    } else if mode.contains(TermMode::APP_CURSOR) {
+        vec![0x1b, b'O', c]
+    } else {
+        vec![0x1b, b'[', c]
+    }
+}
+
+fn tilde_key(number: u8, param: u8) -> Vec<u8> {
+    if param > 1 {
+        format!("\x1b[{};{}~", number, param).into_bytes()
+    } else {
+        format!("\x1b[{}~", number).into_bytes()
+    }
+}
+
+fn ss3_key(c: u8, param: u8) -> Vec<u8> {
+    if param > 1 {
+        format!("\x1b[1;{}{}", param, c as char).into_bytes()
+    } else {
+        vec![0x1b, b'O', c]
+    }
+}
+
+// Ctrlと組み合わせたときの制御文字（xtermの割り当て）
+fn control_code(key: Key) -> Option<u8> {
+    let code = match key {
+        // Ctrl+@ / Ctrl+Space / Ctrl+2 は NUL
+        Key::Space | Key::Num2 => 0x00,
+        // Ctrl+[ / Ctrl+3 は ESC
+        Key::OpenBracket | Key::Num3 => 0x1b,
+        // Ctrl+\ / Ctrl+4
+        Key::Backslash | Key::Num4 => 0x1c,
+        // Ctrl+] / Ctrl+5
+        Key::CloseBracket | Key::Num5 => 0x1d,
+        // Ctrl+^ / Ctrl+6
+        Key::Num6 => 0x1e,
+        // Ctrl+_ / Ctrl+- / Ctrl+/ / Ctrl+7
+        Key::Minus | Key::Slash | Key::Num7 => 0x1f,
+        // Ctrl+? / Ctrl+8 は DEL
+        Key::Questionmark | Key::Num8 => 0x7f,
+        _ => {
+            // Ctrl+A～Zは1～26のコードに変換
+            let c = single_letter(key)?;
+            c - b'A' + 1
+        },
+    };
+    Some(code)
+}
+
+// A～Zのキーであればその文字を返す
+fn single_letter(key: Key) -> Option<u8> {
+    match key.name().as_bytes() {
+        [c] if c.is_ascii_uppercase() => Some(*c),
+        _ => None,
+    }
+}
+
+// 押すと文字が入力されるキーかどうか
+fn is_text_key(key: Key) -> bool {
+    key_char(key).is_some()
+}
+
+// 文字キーが入力する（Shiftなしの）文字
+fn key_char(key: Key) -> Option<char> {
+    if let Some(c) = single_letter(key) {
+        return Some(c.to_ascii_lowercase() as char);
+    }
+    if let [c] = key.name().as_bytes() {
+        if c.is_ascii_digit() {
+            return Some(*c as char);
+        }
+    }
+    
+    let c = match key {
+        Key::Space => ' ',
+        Key::Colon => ':',
+        Key::Comma => ',',
+        Key::Backslash => '\\',
+        Key::Slash => '/',
+        Key::Pipe => '|',
+        Key::Questionmark => '?',
+        Key::Exclamationmark => '!',
+        Key::OpenBracket => '[',
+        Key::CloseBracket => ']',
+        Key::OpenCurlyBracket => '{',
+        Key::CloseCurlyBracket => '}',
+        Key::Backtick => '`',
+        Key::Minus => '-',
+        Key::Period => '.',
+        Key::Plus => '+',
+        Key::Equals => '=',
+        Key::Semicolon => ';',
+        Key::Quote => '\'',
+        _ => return None,
+    };
+    Some(c)
+}
+
+// キーイベントの種類（kittyキーボードプロトコルのイベント種別）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum KeyState {
+    Pressed,
+    Repeated,
+    Released,
+}
+
+impl KeyState {
+    pub(super) fn new(pressed: bool, repeat: bool) -> Self {
+        match (pressed, repeat) {
+            (false, _) => Self::Released,
+            (true, true) => Self::Repeated,
+            (true, false) => Self::Pressed,
+        }
+    }
+}
+
+// kittyキーボードプロトコル（CSI > flags u で有効化）に従ってキーを変換する
+//
+// `text` はキーに続く Text イベントの文字列。Some を返した押下イベントでは
+// 文字もシーケンスに含まれるため、呼び出し側は Text イベントを送らないこと。
+// None の場合は従来どおり encode_key と Text イベントで送信する。
+pub(super) fn encode_kitty_key(
+    key: Key,
+    physical_key: Option<Key>,
+    modifiers: Modifiers,
+    state: KeyState,
+    text: Option<&str>,
+    mode: TermMode,
+) -> Option<Vec<u8>> {
+    if !mode.intersects(TermMode::KITTY_KEYBOARD_PROTOCOL) {
+        return None;
+    }
+    
+    let report_all = mode.contains(TermMode::REPORT_ALL_KEYS_AS_ESC);
+    let report_events = mode.contains(TermMode::REPORT_EVENT_TYPES);
+    let param = modifier_param(modifiers);
+    
+    // 文字キーはShiftを除いた修飾があるときだけエスケープシーケンスにする
+    let (code, terminator, reported) = match kitty_functional_key(key) {
+        Some((code, terminator)) => {
+            let reported = key == Key::Escape || param > 1;
+            (code, terminator, reported)
+        },
+        None => {
+            // Shift併用時は論理キーが記号に変わるため、物理キーから元の文字を求める
+            let base = match physical_key {
+                Some(physical) if modifiers.shift && key_char(physical).is_some() => physical,
+                _ => key,
+            };
+            let reported = modifiers.alt || modifiers.ctrl || modifiers.mac_cmd;
+            (key_char(base)? as u32, 'u', reported)
+        },
+    };
+    
+    match state {
+        // 離したキーはイベント種別の報告が要求されたときだけ送る。
+        // Enter/Tab/Backspace は全キー報告モード以外では送らない
+        KeyState::Released if !report_events => return Some(Vec::new()),
+        KeyState::Released if !report_all && matches!(key, Key::Enter | Key::Tab | Key::Backspace) => {
+            return Some(Vec::new());
+        },
+        KeyState::Released => {},
+        _ if !reported && !report_all => return None,
+        _ => {},
+    }
+    
+    // 第1パラメータ: キーコード（とShift時の代替キー）
+    let mut key_param = code.to_string();
+    if mode.contains(TermMode::REPORT_ALTERNATE_KEYS) && modifiers.shift && terminator == 'u' {
+        let shifted = match text.map(|text| text.chars().collect::<Vec<_>>()).as_deref() {
+            Some(&[c]) => Some(c),
+            _ => char::from_u32(code).map(|c| c.to_ascii_uppercase()),
+        };
+        if let Some(shifted) = shifted.filter(|c| *c as u32 != code) {
+            key_param.push_str(&format!(":{}", shifted as u32));
+        }
+    }
+    
+    // 第2パラメータ: 修飾キーとイベント種別
+    let mut modifier_params = String::new();
+    let event_type = match state {
+        KeyState::Repeated if report_events => Some(2),
+        KeyState::Released => Some(3),
+        _ => None,
+    };
+    if param > 1 || event_type.is_some() {
+        modifier_params = param.to_string();
+    }
+    if let Some(event_type) = event_type {
+        modifier_params.push_str(&format!(":{}", event_type));
+    }
+    
+    // 第3パラメータ: 入力される文字のコードポイント
+    let mut text_params = String::new();
+    if mode.contains(TermMode::REPORT_ASSOCIATED_TEXT) && report_all && state != KeyState::Released {
+        let codepoints: Vec<String> = text
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| !c.is_control())
+            .map(|c| (c as u32).to_string())
+            .collect();
+        text_params = codepoints.join(":");
+    }
+    
+    let mut params = key_param;
+    if !text_params.is_empty() {
+        params = format!("{};{};{}", params, modifier_params, text_params);
+    } else if !modifier_params.is_empty() {
+        params = format!("{};{}", params, modifier_params);
+    } else if params == "1" {
+        // 修飾なしのカーソルキーなどは CSI A の形式にする
+        params.clear();
+    }
+    
+    Some(format!("\x1b[{}{}", params, terminator).into_bytes())
+}
+
+// 文字を入力しないキーのkittyプロトコルでのコードと終端文字
+fn kitty_functional_key(key: Key) -> Option<(u32, char)> {
+    let functional = match key {
+        Key::Escape => (27, 'u'),
+        Key::Enter => (13, 'u'),
+        Key::Tab => (9, 'u'),
+        Key::Backspace => (127, 'u'),
+        Key::Insert => (2, '~'),
+        Key::Delete => (3, '~'),
+        Key::ArrowLeft => (1, 'D'),
+        Key::ArrowRight => (1, 'C'),
+        Key::ArrowUp => (1, 'A'),
+        Key::ArrowDown => (1, 'B'),
+        Key::PageUp => (5, '~'),
+        Key::PageDown => (6, '~'),
+        Key::Home => (1, 'H'),
+        Key::End => (1, 'F'),
+        Key::F1 => (1, 'P'),
+        Key::F2 => (1, 'Q'),
+        Key::F3 => (13, '~'),
+        Key::F4 => (1, 'S'),
+        Key::F5 => (15, '~'),
+        Key::F6 => (17, '~'),
+        Key::F7 => (18, '~'),
+        Key::F8 => (19, '~'),
+        Key::F9 => (20, '~'),
+        Key::F10 => (21, '~'),
+        Key::F11 => (23, '~'),
+        Key::F12 => (24, '~'),
+        // F13以降は私用領域のコードを使う
+        Key::F13 => (57376, 'u'),
+        Key::F14 => (57377, 'u'),
+        Key::F15 => (57378, 'u'),
+        Key::F16 => (57379, 'u'),
+        Key::F17 => (57380, 'u'),
+        Key::F18 => (57381, 'u'),
+        Key::F19 => (57382, 'u'),
+        Key::F20 => (57383, 'u'),
+        _ => return None,
+    };
+    Some(functional)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn cursor_keys_follow_decckm() {
+        assert_eq!(encode_key(Key::ArrowUp, Modifiers::NONE, TermMode::NONE).unwrap(), b"\x1b[A");
+        assert_eq!(encode_key(Key::ArrowUp, Modifiers::NONE, TermMode::APP_CURSOR).unwrap(), b"\x1bOA");
+        assert_eq!(encode_key(Key::Home, Modifiers::NONE, TermMode::APP_CURSOR).unwrap(), b"\x1bOH");
+    }
+    
+    #[test]
+    fn modified_keys_use_xterm_parameters() {
+        // 修飾キーがあるとDECCKMに関係なくCSI 1;<修飾> の形式になる
+        assert_eq!(encode_key(Key::ArrowRight, Modifiers::CTRL, TermMode::APP_CURSOR).unwrap(), b"\x1b[1;5C");
+        assert_eq!(encode_key(Key::ArrowLeft, Modifiers::ALT, TermMode::NONE).unwrap(), b"\x1b[1;3D");
+        assert_eq!(encode_key(Key::ArrowUp, Modifiers::CTRL | Modifiers::SHIFT, TermMode::NONE).unwrap(), b"\x1b[1;6A");
+        assert_eq!(encode_key(Key::Delete, Modifiers::SHIFT, TermMode::NONE).unwrap(), b"\x1b[3;2~");
+        assert_eq!(encode_key(Key::F5, Modifiers::NONE, TermMode::NONE).unwrap(), b"\x1b[15~");
+    }
+    
+    #[test]
+    fn function_keys() {
+        assert_eq!(encode_key(Key::F1, Modifiers::NONE, TermMode::NONE).unwrap(), b"\x1bOP");
+        assert_eq!(encode_key(Key::F4, Modifiers::CTRL, TermMode::NONE).unwrap(), b"\x1b[1;5S");
+        assert_eq!(encode_key(Key::F12, Modifiers::NONE, TermMode::NONE).unwrap(), b"\x1b[24~");
+    }
+    
+    #[test]
+    fn control_and_alt_keys() {
+        assert_eq!(encode_key(Key::A, Modifiers::CTRL, TermMode::NONE).unwrap(), b"\x01");
+        assert_eq!(encode_key(Key::C, Modifiers::CTRL | Modifiers::ALT, TermMode::NONE).unwrap(), b"\x1b\x03");
+        assert_eq!(encode_key(Key::Space, Modifiers::CTRL, TermMode::NONE).unwrap(), b"\x00");
+        assert_eq!(encode_key(Key::OpenBracket, Modifiers::CTRL, TermMode::NONE).unwrap(), b"\x1b");
+        // Alt+文字は ESC だけを返し、文字はTextイベントで送る
+        assert_eq!(encode_key(Key::X, Modifiers::ALT, TermMode::NONE).unwrap(), b"\x1b");
+        assert_eq!(encode_key(Key::X, Modifiers::NONE, TermMode::NONE), None);
+    }
+    
+    #[test]
+    fn editing_keys() {
+        assert_eq!(encode_key(Key::Tab, Modifiers::SHIFT, TermMode::NONE).unwrap(), b"\x1b[Z");
+        assert_eq!(encode_key(Key::Backspace, Modifiers::NONE, TermMode::NONE).unwrap(), b"\x7f");
+        assert_eq!(encode_key(Key::Backspace, Modifiers::CTRL, TermMode::NONE).unwrap(), b"\x08");
+        assert_eq!(encode_key(Key::Enter, Modifiers::NONE, TermMode::NONE).unwrap(), b"\r");
+        assert_eq!(encode_key(Key::Enter, Modifiers::NONE, TermMode::LINE_FEED_NEW_LINE).unwrap(), b"\r\n");
+    }
+    
+    // kittyプロトコルの押下イベント（物理キーは論理キーと同じとする）
+    fn kitty(key: Key, modifiers: Modifiers, text: Option<&str>, mode: TermMode) -> Option<Vec<u8>> {
+        encode_kitty_key(key, Some(key), modifiers, KeyState::Pressed, text, mode)
+    }
+    
+    #[test]
+    fn kitty_disabled_falls_back_to_legacy() {
+        assert_eq!(kitty(Key::C, Modifiers::CTRL, None, TermMode::NONE), None);
+    }
+    
+    #[test]
+    fn kitty_disambiguate_escape_codes() {
+        let mode = TermMode::DISAMBIGUATE_ESC_CODES;
+        assert_eq!(kitty(Key::C, Modifiers::CTRL, None, mode).unwrap(), b"\x1b[99;5u");
+        assert_eq!(kitty(Key::A, Modifiers::ALT, Some("a"), mode).unwrap(), b"\x1b[97;3u");
+        assert_eq!(kitty(Key::Escape, Modifiers::NONE, None, mode).unwrap(), b"\x1b[27u");
+        assert_eq!(kitty(Key::ArrowRight, Modifiers::CTRL, None, mode).unwrap(), b"\x1b[1;5C");
+        assert_eq!(kitty(Key::F5, Modifiers::SHIFT, None, mode).unwrap(), b"\x1b[15;2~");
+        // 修飾のない文字キーとカーソルキーは従来どおり送る
+        assert_eq!(kitty(Key::A, Modifiers::NONE, Some("a"), mode), None);
+        assert_eq!(kitty(Key::ArrowUp, Modifiers::NONE, None, mode), None);
+    }
+    
+    #[test]
+    fn kitty_release_events() {
+        let release = |mode| encode_kitty_key(Key::C, Some(Key::C), Modifiers::CTRL, KeyState::Released, None, mode);
+        // イベント種別の報告がなければ離したキーは何も送らない
+        assert_eq!(release(TermMode::DISAMBIGUATE_ESC_CODES).unwrap(), b"");
+        let mode = TermMode::DISAMBIGUATE_ESC_CODES | TermMode::REPORT_EVENT_TYPES;
+        assert_eq!(release(mode).unwrap(), b"\x1b[99;5:3u");
+        
+        let repeat = encode_kitty_key(Key::ArrowUp, Some(Key::ArrowUp), Modifiers::NONE, KeyState::Repeated, None, mode);
+        assert_eq!(repeat, None);
+        let repeat = encode_kitty_key(Key::ArrowUp, Some(Key::ArrowUp), Modifiers::SHIFT, KeyState::Repeated, None, mode);
+        assert_eq!(repeat.unwrap(), b"\x1b[1;2:2A");
+    }
+    
+    #[test]
+    fn kitty_report_all_keys() {
+        let mode = TermMode::REPORT_ALL_KEYS_AS_ESC;
+        assert_eq!(kitty(Key::A, Modifiers::NONE, Some("a"), mode).unwrap(), b"\x1b[97u");
+        assert_eq!(kitty(Key::Enter, Modifiers::NONE, None, mode).unwrap(), b"\x1b[13u");
+        assert_eq!(kitty(Key::ArrowUp, Modifiers::NONE, None, mode).unwrap(), b"\x1b[A");
+        
+        let mode = mode | TermMode::REPORT_ASSOCIATED_TEXT;
+        assert_eq!(kitty(Key::A, Modifiers::NONE, Some("a"), mode).unwrap(), b"\x1b[97;;97u");
+    }
+    
+    #[test]
+    fn kitty_alternate_keys() {
+        let mode = TermMode::REPORT_ALL_KEYS_AS_ESC | TermMode::REPORT_ALTERNATE_KEYS;
+        assert_eq!(kitty(Key::A, Modifiers::SHIFT, Some("A"), mode).unwrap(), b"\x1b[97:65;2u");
+        // Shift併用時は物理キーから元の文字を求める
+        let encoded = encode_kitty_key(Key::Exclamationmark, Some(Key::Num1), Modifiers::SHIFT, KeyState::Pressed, Some("!"), mode);
+        assert_eq!(encoded.unwrap(), b"\x1b[49:33;2u");
+    }
+}
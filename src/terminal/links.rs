@@ -0,0 +1,455 @@
+use std::path::{Path, PathBuf};
+use egui::{CursorIcon, Rect};
+use alacritty_terminal::index::{Boundary, Direction, Point};
+use alacritty_terminal::term::Term;
+use alacritty_terminal::term::cell::Hyperlink;
+use alacritty_terminal::term::search::{Match, RegexIter, RegexSearch};
+
+use super::{shell_integration, ShellCommand, TerminalPane};
+
+// URLとして検出するパターン（Alacrittyの既定値と同じ）
+pub(super) const URL_REGEX: &str = "(ipfs:|ipns:|magnet:|mailto:|gemini://|gopher://|https://|http://|news:|file:|git://|ssh:|ftp://)\
+                         [^\u{0000}-\u{001F}\u{007F}-\u{009F}<>\"\\s{-}\\^⟨⟩`]+";
+
+// コンパイラのエラー出力などに含まれる `path:line:col` のパターン
+pub(super) const PATH_REGEX: &str = r"(?:~|\.{1,2})?/?(?:[\w.\-]+/)*[\w\-][\w.\-]*\.[A-Za-z0-9]+:[0-9]+(?::[0-9]+)?";
+
+// URLの末尾に付いていても含めない文字
+const URL_TRAILING_CHARS: &[char] = &['.', ',', ':', ';', '!', '?', '\'', '"'];
+
+// チャットに送るときにファイルから引用する前後の行数
+const CONTEXT_LINES: usize = 5;
+
+// ファイル内の位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileLocation {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl FileLocation {
+    // `path:line:col` 形式の文字列を解析する
+    pub(super) fn parse(text: &str) -> Self {
+        // 末尾から順に、数値の部分を行番号と列番号として取り出す
+        let mut parts: Vec<&str> = text.rsplitn(3, ':').collect();
+        let mut numbers: Vec<usize> = Vec::new();
+        while parts.len() > 1 {
+            let Ok(number) = parts[0].parse() else {
+                break;
+            };
+            numbers.insert(0, number);
+            parts.remove(0);
+        }
+        parts.reverse();
+        let path = parts.join(":");
+        
+        Self {
+            path: PathBuf::from(path),
+            line: numbers.first().copied(),
+            column: numbers.get(1).copied(),
+        }
+    }
+    
+    // $VISUAL または $EDITOR でこの位置を開くコマンド
+    pub fn editor_command(&self) -> ShellCommand {
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+        
+        // `code -w` のように引数付きで指定されている場合がある
+        let mut words = editor.split_whitespace().map(str::to_string);
+        let program = words.next().unwrap_or_else(|| "vi".to_string());
+        let mut args: Vec<String> = words.collect();
+        args.extend(self.editor_args(&program));
+        
+        ShellCommand {
+            program,
+            args,
+            working_directory: self.path.parent().map(Path::to_path_buf),
+        }
+    }
+    
+    // エディタごとの行番号の指定方法に合わせた引数
+    //
+    // 行番号の指定方法がわからないエディタにはパスだけを渡す。
+    fn editor_args(&self, program: &str) -> Vec<String> {
+        let path = self.path.display().to_string();
+        let Some(line) = self.line else {
+            return vec![path];
+        };
+        
+        let name = Path::new(program).file_name().and_then(|name| name.to_str()).unwrap_or(program);
+        let with_position = match self.column {
+            Some(column) => format!("{}:{}:{}", path, line, column),
+            None => format!("{}:{}", path, line),
+        };
+        match name {
+            "code" | "code-insiders" | "codium" => vec!["-g".to_string(), with_position],
+            "subl" | "hx" | "helix" | "zed" => vec![with_position],
+            "vi" | "vim" | "nvim" | "view" | "emacs" | "emacsclient" | "nano" => vec![format!("+{}", line), path],
+            _ => vec![path],
+        }
+    }
+    
+    // チャットに送るための、位置と周辺のソースコードをまとめたテキスト
+    pub fn chat_context(&self) -> String {
+        let mut context = self.to_string();
+        
+        let Some(line) = self.line else {
+            return context;
+        };
+        let Ok(source) = std::fs::read_to_string(&self.path) else {
+            return context;
+        };
+        
+        let first = line.saturating_sub(CONTEXT_LINES + 1);
+        let excerpt: Vec<String> = source
+            .lines()
+            .enumerate()
+            .skip(first)
+            .take(CONTEXT_LINES * 2 + 1)
+            .map(|(i, text)| format!("{:>5} | {}", i + 1, text))
+            .collect();
+        if !excerpt.is_empty() {
+            let language = self.path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+            context.push_str(&format!("\n```{}\n{}\n```", language, excerpt.join("\n")));
+        }
+        context
+    }
+}
+
+impl std::fmt::Display for FileLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+// リンクの操作のうち、ターミナルの外で処理するもの
+#[derive(Debug, Clone)]
+pub enum LinkAction {
+    // ファイルをエディタで開く
+    OpenInEditor(FileLocation),
+    // テキストをチャットパネルに送る
+    SendToChat(String),
+}
+
+// リンクの参照先
+#[derive(Debug, Clone)]
+pub(super) enum LinkTarget {
+    Url(String),
+    File(FileLocation),
+}
+
+// グリッド上のリンク
+#[derive(Debug, Clone)]
+pub(super) struct Link {
+    pub range: Match,
+    pub target: LinkTarget,
+}
+
+// OSC 8 のハイパーリンクと、テキスト中のURL・ファイルパスを検出する
+pub(super) struct LinkDetector {
+    url: RegexSearch,
+    path: RegexSearch,
+}
+
+impl LinkDetector {
+    pub(super) fn new() -> Self {
+        Self {
+            url: RegexSearch::new(URL_REGEX).expect("URLの正規表現が不正です"),
+            path: RegexSearch::new(PATH_REGEX).expect("パスの正規表現が不正です"),
+        }
+    }
+    
+    // 指定した位置にあるリンクを探す
+    //
+    // 相対パスは `cwd` を基準に解決する。
+    pub(super) fn link_at<T>(&mut self, term: &Term<T>, point: Point, cwd: Option<&Path>) -> Option<Link> {
+        // OSC 8 で設定されたハイパーリンクを優先する（シェル統合の目印は除く）
+        let hyperlink = term.grid()[point].hyperlink().filter(|hyperlink| !shell_integration::is_mark(hyperlink));
+        if let Some(hyperlink) = hyperlink {
+            let range = hyperlink_range(term, point, &hyperlink);
+            return Some(Link { range, target: hyperlink_target(&hyperlink) });
+        }
+        
+        // 折り返された行全体を対象に正規表現で検索する
+        let start = term.line_search_left(point);
+        let end = term.line_search_right(point);
+        
+        if let Some(range) = find_match(term, &mut self.url, start, end, point) {
+            let range = trim_url(term, range);
+            if range.contains(&point) {
+                let url = term.bounds_to_string(*range.start(), *range.end());
+                return Some(Link { range, target: LinkTarget::Url(url) });
+            }
+        }
+        
+        let range = find_match(term, &mut self.path, start, end, point)?;
+        let mut location = FileLocation::parse(&term.bounds_to_string(*range.start(), *range.end()));
+        location.path = resolve_path(&location.path, cwd);
+        Some(Link { range, target: LinkTarget::File(location) })
+    }
+}
+
+impl TerminalPane {
+    // ポインタ下のリンクを検出し、Ctrl+クリックで開く
+    //
+    // 右クリックではリンクに対する操作のメニューを表示する。
+    // クリックをリンクの操作として処理した場合は true を返す。
+    pub(super) fn handle_links(&mut self, ui: &egui::Ui, response: &egui::Response, rect: Rect) -> bool {
+        let (pos, ctrl, pressed) = ui.input(|i| (i.pointer.hover_pos(), i.modifiers.command, i.pointer.primary_pressed()));
+        
+        self.hovered_link = match pos {
+            Some(pos) if response.hovered() && rect.contains(pos) => {
+                let (point, _) = self.point_at(rect, pos);
+                let cwd = self.working_directory();
+                let term = self.term.lock();
+                self.link_detector.link_at(&term, point, cwd.as_deref())
+            },
+            _ => None,
+        };
+        
+        if response.secondary_clicked() {
+            self.menu_link = self.hovered_link.clone();
+        }
+        if let Some(link) = self.menu_link.clone() {
+            let menu = response.context_menu(|ui| self.link_menu_ui(ui, &link));
+            if menu.is_none() {
+                self.menu_link = None;
+            }
+        }
+        
+        let Some(link) = &self.hovered_link else {
+            return false;
+        };
+        if !ctrl {
+            return false;
+        }
+        
+        ui.ctx().set_cursor_icon(CursorIcon::PointingHand);
+        if !pressed {
+            return false;
+        }
+        
+        let target = link.target.clone();
+        self.open_link(ui.ctx(), &target);
+        true
+    }
+    
+    // リンクを開く。URLはブラウザで、ファイルはエディタで開く
+    pub(super) fn open_link(&mut self, ctx: &egui::Context, target: &LinkTarget) {
+        match target {
+            LinkTarget::Url(url) => ctx.open_url(egui::OpenUrl::new_tab(url)),
+            LinkTarget::File(location) => self.link_actions.push(LinkAction::OpenInEditor(location.clone())),
+        }
+    }
+    
+    // リンクに対する操作のメニュー
+    fn link_menu_ui(&mut self, ui: &mut egui::Ui, link: &Link) {
+        match &link.target {
+            LinkTarget::Url(url) => {
+                if ui.button("リンクを開く").clicked() {
+                    self.open_link(ui.ctx(), &link.target);
+                    ui.close_menu();
+                }
+                if ui.button("リンクをコピー").clicked() {
+                    ui.ctx().copy_text(url.clone());
+                    ui.close_menu();
+                }
+            },
+            LinkTarget::File(location) => {
+                if ui.button("エディタで開く").clicked() {
+                    self.open_link(ui.ctx(), &link.target);
+                    ui.close_menu();
+                }
+                if ui.button("チャットに送る").clicked() {
+                    self.link_actions.push(LinkAction::SendToChat(location.chat_context()));
+                    ui.close_menu();
+                }
+                if ui.button("パスをコピー").clicked() {
+                    ui.ctx().copy_text(location.to_string());
+                    ui.close_menu();
+                }
+            },
+        }
+    }
+    
+    // ターミナルの外で処理するリンクの操作を取り出す
+    pub fn take_link_actions(&mut self) -> Vec<LinkAction> {
+        std::mem::take(&mut self.link_actions)
+    }
+}
+
+// 指定した位置を含む一致を探す
+fn find_match<T>(term: &Term<T>, regex: &mut RegexSearch, start: Point, end: Point, point: Point) -> Option<Match> {
+    RegexIter::new(start, end, Direction::Right, term, regex)
+        .take_while(|range| *range.start() <= point)
+        .find(|range| range.contains(&point))
+}
+
+// OSC 8 のハイパーリンクの参照先
+pub(super) fn hyperlink_target(hyperlink: &Hyperlink) -> LinkTarget {
+    match hyperlink.uri().strip_prefix("file://") {
+        // file://ホスト名/パス の形式
+        Some(rest) => {
+            let path = rest.find('/').map_or(rest, |i| &rest[i..]);
+            // クエリとフラグメントを除き、%エンコードを戻す
+            let path = path.split(['?', '#']).next().unwrap_or_default();
+            LinkTarget::File(FileLocation { path: PathBuf::from(percent_decode(path)), line: None, column: None })
+        },
+        None => LinkTarget::Url(hyperlink.uri().to_string()),
+    }
+}
+
+// URIの%エンコードを戻す（不正な並びはそのまま残す）
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.filter(|_| bytes[i] == b'%').and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// 同じハイパーリンクが続く範囲
+fn hyperlink_range<T>(term: &Term<T>, point: Point, hyperlink: &Hyperlink) -> Match {
+    let matches = |point: Point| term.grid()[point].hyperlink().as_ref() == Some(hyperlink);
+    
+    let mut start = point;
+    loop {
+        let previous = start.sub(term, Boundary::Grid, 1);
+        if previous == start || !matches(previous) {
+            break;
+        }
+        start = previous;
+    }
+    
+    let mut end = point;
+    loop {
+        let next = end.add(term, Boundary::Grid, 1);
+        if next == end || !matches(next) {
+            break;
+        }
+        end = next;
+    }
+    
+    start..=end
+}
+
+// URLの末尾の句読点や対応しない閉じ括弧を取り除く
+pub(super) fn trim_url<T>(term: &Term<T>, range: Match) -> Match {
+    let url = term.bounds_to_string(*range.start(), *range.end());
+    
+    let mut trimmed = url.as_str();
+    loop {
+        let unbalanced = |open: char, close: char| {
+            trimmed.ends_with(close) && trimmed.matches(close).count() > trimmed.matches(open).count()
+        };
+        if trimmed.ends_with(URL_TRAILING_CHARS) || unbalanced('(', ')') || unbalanced('[', ']') {
+            trimmed = &trimmed[..trimmed.len() - 1];
+        } else {
+            break;
+        }
+    }
+    
+    // 取り除いた文字はすべて1セル幅のASCII文字
+    let removed = url.len() - trimmed.len();
+    let end = range.end().sub(term, Boundary::Grid, removed);
+    *range.start()..=end
+}
+
+// 相対パスを作業ディレクトリを基準に、`~` をホームディレクトリに展開する
+pub(super) fn resolve_path(path: &Path, cwd: Option<&Path>) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = std::env::var_os("HOME") {
+            return PathBuf::from(home).join(rest);
+        }
+    }
+    
+    match cwd {
+        Some(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn location(path: &str, line: Option<usize>, column: Option<usize>) -> FileLocation {
+        FileLocation { path: PathBuf::from(path), line, column }
+    }
+    
+    #[test]
+    fn parse_file_location() {
+        assert_eq!(FileLocation::parse("src/main.rs:12:5"), location("src/main.rs", Some(12), Some(5)));
+        assert_eq!(FileLocation::parse("src/main.rs:12"), location("src/main.rs", Some(12), None));
+        assert_eq!(FileLocation::parse("src/main.rs"), location("src/main.rs", None, None));
+        // パスに含まれるコロンは残す
+        assert_eq!(FileLocation::parse("a:b/c.rs:3:4"), location("a:b/c.rs", Some(3), Some(4)));
+        // 末尾が数値でなければ行番号とみなさない
+        assert_eq!(FileLocation::parse("x:12:y"), location("x:12:y", None, None));
+    }
+    
+    #[test]
+    fn display_file_location() {
+        for text in ["src/main.rs:12:5", "src/main.rs:12", "src/main.rs"] {
+            assert_eq!(FileLocation::parse(text).to_string(), text);
+        }
+    }
+    
+    #[test]
+    fn resolve_relative_path() {
+        let cwd = Path::new("/home/user/project");
+        assert_eq!(resolve_path(Path::new("src/main.rs"), Some(cwd)), cwd.join("src/main.rs"));
+        assert_eq!(resolve_path(Path::new("/etc/hosts"), Some(cwd)), PathBuf::from("/etc/hosts"));
+        assert_eq!(resolve_path(Path::new("src/main.rs"), None), PathBuf::from("src/main.rs"));
+    }
+    
+    #[test]
+    fn editor_line_arguments() {
+        let at_line = location("/src/main.rs", Some(12), None);
+        assert_eq!(at_line.editor_args("nvim"), ["+12", "/src/main.rs"]);
+        assert_eq!(at_line.editor_args("/usr/bin/vim"), ["+12", "/src/main.rs"]);
+        assert_eq!(at_line.editor_args("code"), ["-g", "/src/main.rs:12"]);
+        assert_eq!(at_line.editor_args("hx"), ["/src/main.rs:12"]);
+        // 行番号の指定方法がわからないエディタにはパスだけを渡す
+        assert_eq!(at_line.editor_args("gedit"), ["/src/main.rs"]);
+        
+        let at_column = location("/src/main.rs", Some(12), Some(5));
+        assert_eq!(at_column.editor_args("subl"), ["/src/main.rs:12:5"]);
+        assert_eq!(location("/src/main.rs", None, None).editor_args("code"), ["/src/main.rs"]);
+    }
+    
+    #[test]
+    fn file_hyperlink_path() {
+        let path = |uri: &str| match hyperlink_target(&Hyperlink::new(None::<String>, uri.to_string())) {
+            LinkTarget::File(location) => Some(location.path),
+            LinkTarget::Url(_) => None,
+        };
+        assert_eq!(path("file:///tmp/a%20b.txt"), Some(PathBuf::from("/tmp/a b.txt")));
+        assert_eq!(path("file://host/tmp/%E3%81%82.txt?x=1#L3"), Some(PathBuf::from("/tmp/あ.txt")));
+        // 不正な%エンコードはそのまま残す
+        assert_eq!(path("file:///tmp/100%.txt"), Some(PathBuf::from("/tmp/100%.txt")));
+        assert_eq!(path("https://example.com/a%20b"), None);
+    }
+}
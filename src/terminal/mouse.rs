@@ -0,0 +1,336 @@
+use egui::{Modifiers, PointerButton, Pos2, Rect};
+use std::time::{Duration, Instant};
+use alacritty_terminal::grid::{Dimensions, Scroll};
+use alacritty_terminal::index::{Column, Point, Side};
+use alacritty_terminal::selection::{Selection, SelectionType};
+use alacritty_terminal::term::{viewport_to_point, TermMode};
+
+use super::TerminalPane;
+
+// ダブルクリック・トリプルクリックとみなすクリック間隔
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+// xtermのマウスプロトコルにおけるボタン番号
+const BUTTON_RELEASE: u8 = 3;
+const BUTTON_WHEEL_UP: u8 = 64;
+const BUTTON_WHEEL_DOWN: u8 = 65;
+const MOTION_FLAG: u8 = 32;
+
+// マウス操作の状態
+#[derive(Default)]
+pub(super) struct MouseState {
+    last_click: Option<(Instant, Point)>,
+    click_count: u8,
+    selecting: bool,
+    pressed_button: Option<u8>, // マウス報告中に押されているボタン
+    last_report_point: Option<Point<usize>>, // 最後に移動を報告したセル
+}
+
+impl TerminalPane {
+    // マウスによるテキスト選択
+    //
+    // シングルクリックは文字単位（Alt併用で矩形）、ダブルクリックは単語、
+    // トリプルクリックは行単位で選択する。
+    pub(super) fn handle_selection(&mut self, ui: &egui::Ui, response: &egui::Response, rect: Rect) {
+        let (pressed, down, alt, pos) = ui.input(|i| {
+            (
+                i.pointer.primary_pressed(),
+                i.pointer.primary_down(),
+                i.modifiers.alt,
+                i.pointer.interact_pos(),
+            )
+        });
+        let Some(pos) = pos else {
+            return;
+        };
+        
+        if pressed && response.hovered() {
+            let (point, side) = self.point_at(rect, pos);
+            
+            // 同じ位置での連続クリック回数を数える
+            let now = Instant::now();
+            self.mouse.click_count = match self.mouse.last_click {
+                Some((time, last)) if now - time < MULTI_CLICK_INTERVAL && last == point => {
+                    self.mouse.click_count % 3 + 1
+                },
+                _ => 1,
+            };
+            self.mouse.last_click = Some((now, point));
+            
+            let ty = match self.mouse.click_count {
+                1 if alt => SelectionType::Block,
+                1 => SelectionType::Simple,
+                2 => SelectionType::Semantic,
+                _ => SelectionType::Lines,
+            };
+            self.term.lock().selection = Some(Selection::new(ty, point, side));
+            self.mouse.selecting = true;
+        } else if self.mouse.selecting && down {
+            // ペイン外までドラッグした場合は表示をスクロールさせて選択を広げる
+            if pos.y < rect.top() {
+                self.term.lock().scroll_display(Scroll::Delta(1));
+            } else if pos.y > rect.bottom() {
+                self.term.lock().scroll_display(Scroll::Delta(-1));
+            }
+            
+            let (point, side) = self.point_at(rect, pos);
+            if let Some(selection) = &mut self.term.lock().selection {
+                selection.update(point, side);
+            }
+        } else if !down {
+            self.mouse.selecting = false;
+        }
+    }
+    
+    // アプリケーションがマウス報告を要求しているかどうか
+    pub(super) fn mouse_reporting(&self) -> bool {
+        self.term.lock().mode().intersects(TermMode::MOUSE_MODE) || self.extra_modes.x10_mouse()
+    }
+    
+    // クリック・ドラッグ・移動をマウス報告としてPTYへ送る
+    //
+    // X10（DECSET 9）は押下のみ、1000は押下と解放、1002はボタンを押したままの移動、
+    // 1003はすべての移動を報告する。
+    pub(super) fn report_mouse(&mut self, ui: &egui::Ui, response: &egui::Response, rect: Rect) {
+        let (events, modifiers) = ui.input(|i| (i.events.clone(), i.modifiers));
+        let mode = *self.term.lock().mode();
+        
+        // 検索バーや設定ウィンドウ、メニューなどが上に重なっている位置の操作は報告しない
+        let over_terminal = |pos: Pos2| {
+            rect.contains(pos) && response.hovered() && ui.ctx().layer_id_at(pos) == Some(ui.layer_id())
+        };
+        
+        for event in events {
+            match event {
+                egui::Event::PointerButton { pos, button, pressed, modifiers } => {
+                    let Some(code) = button_code(button) else {
+                        continue;
+                    };
+                    
+                    // 押下はポインタ下がペインのときのみ、解放は押したボタンのみを報告する
+                    if pressed && !over_terminal(pos) {
+                        continue;
+                    }
+                    if !pressed && self.mouse.pressed_button != Some(code) {
+                        continue;
+                    }
+                    
+                    self.mouse.pressed_button = pressed.then_some(code);
+                    let point = self.viewport_point(rect, pos);
+                    self.mouse.last_report_point = Some(point);
+                    self.send_mouse_report(code, pressed, point, modifiers);
+                },
+                egui::Event::PointerMoved(pos) => {
+                    let report_motion = mode.contains(TermMode::MOUSE_MOTION)
+                        || (mode.contains(TermMode::MOUSE_DRAG) && self.mouse.pressed_button.is_some());
+                    if !report_motion || (!over_terminal(pos) && self.mouse.pressed_button.is_none()) {
+                        continue;
+                    }
+                    
+                    // 同じセル内の移動は報告しない
+                    let point = self.viewport_point(rect, pos);
+                    if self.mouse.last_report_point == Some(point) {
+                        continue;
+                    }
+                    self.mouse.last_report_point = Some(point);
+                    
+                    let code = self.mouse.pressed_button.unwrap_or(BUTTON_RELEASE) + MOTION_FLAG;
+                    self.send_mouse_report(code, true, point, modifiers);
+                },
+                _ => {}
+            }
+        }
+    }
+    
+    // ホイールの回転をボタン4/5の押下として報告する
+    pub(super) fn report_wheel(&mut self, rect: Rect, pos: Pos2, lines: i32, modifiers: Modifiers) {
+        // X10モードはホイールを報告しない
+        if !self.term.lock().mode().intersects(TermMode::MOUSE_MODE) {
+            return;
+        }
+        
+        let code = if lines > 0 { BUTTON_WHEEL_UP } else { BUTTON_WHEEL_DOWN };
+        let point = self.viewport_point(rect, pos);
+        for _ in 0..lines.unsigned_abs() {
+            self.send_mouse_report(code, true, point, modifiers);
+        }
+    }
+    
+    // 現在のエンコーディングで報告を送る
+    fn send_mouse_report(&self, button: u8, pressed: bool, point: Point<usize>, modifiers: Modifiers) {
+        let mode = *self.term.lock().mode();
+        if let Some(report) = encode_mouse_report(button, pressed, point, modifiers, mode, self.extra_modes.urxvt_mouse()) {
+            self.write_to_pty(report);
+        }
+    }
+    
+    // 画面上の座標を表示領域内のセル位置に変換する
+    fn viewport_point(&self, rect: Rect, pos: Pos2) -> Point<usize> {
+        let (columns, screen_lines) = {
+            let term = self.term.lock();
+            (term.grid().columns(), term.grid().screen_lines())
+        };
+        
+        let x = ((pos.x - rect.left()) / self.cell_size.0).max(0.0) as usize;
+        let y = ((pos.y - rect.top()) / self.cell_size.1).max(0.0) as usize;
+        Point::new(y.min(screen_lines - 1), Column(x.min(columns - 1)))
+    }
+    
+    // 画面上の座標をグリッド上の位置と、セルの左右どちら側かに変換する
+    pub(super) fn point_at(&self, rect: Rect, pos: Pos2) -> (Point, Side) {
+        let viewport_point = self.viewport_point(rect, pos);
+        let (columns, display_offset) = {
+            let term = self.term.lock();
+            (term.grid().columns(), term.grid().display_offset())
+        };
+        
+        let x = ((pos.x - rect.left()) / self.cell_size.0).max(0.0);
+        let side = if x.fract() < 0.5 && (x as usize) < columns { Side::Left } else { Side::Right };
+        (viewport_to_point(display_offset, viewport_point), side)
+    }
+    
+    // 選択中のテキストをクリップボードにコピーする
+    pub(super) fn copy_selection(&self, ctx: &egui::Context) {
+        if let Some(text) = self.term.lock().selection_to_string() {
+            ctx.copy_text(text);
+        }
+    }
+}
+
+fn button_code(button: PointerButton) -> Option<u8> {
+    match button {
+        PointerButton::Primary => Some(0),
+        PointerButton::Middle => Some(1),
+        PointerButton::Secondary => Some(2),
+        _ => None,
+    }
+}
+
+// マウス報告のバイト列（SGR 1006 / urxvt 1015 / UTF-8 1005 / 通常）
+//
+// 報告しないイベントや、座標がエンコードできる範囲を超えている場合は None。
+fn encode_mouse_report(
+    button: u8,
+    pressed: bool,
+    point: Point<usize>,
+    modifiers: Modifiers,
+    mode: TermMode,
+    urxvt: bool,
+) -> Option<Vec<u8>> {
+    let x10 = !mode.intersects(TermMode::MOUSE_MODE);
+    
+    // X10モードは押下のみを修飾キーなしで報告する
+    if x10 && !pressed {
+        return None;
+    }
+    
+    let mut mods = 0;
+    if !x10 {
+        if modifiers.shift {
+            mods += 4;
+        }
+        if modifiers.alt {
+            mods += 8;
+        }
+        if modifiers.ctrl {
+            mods += 16;
+        }
+    }
+    
+    let column = point.column.0 + 1;
+    let line = point.line + 1;
+    
+    let report = if mode.contains(TermMode::SGR_MOUSE) {
+        let action = if pressed { 'M' } else { 'm' };
+        format!("\x1b[<{};{};{}{}", button + mods, column, line, action).into_bytes()
+    } else {
+        // SGR以外の形式では解放時のボタンを区別できない
+        let button = (if pressed { button } else { BUTTON_RELEASE }) + mods;
+        
+        if urxvt {
+            format!("\x1b[{};{};{}M", 32 + button as usize, column, line).into_bytes()
+        } else {
+            let mut report = b"\x1b[M".to_vec();
+            report.push(32 + button);
+            let utf8 = mode.contains(TermMode::UTF8_MOUSE);
+            for position in [column, line] {
+                if !encode_position(&mut report, position, utf8) {
+                    return None;
+                }
+            }
+            report
+        }
+    };
+    Some(report)
+}
+
+// 通常形式のマウス報告の座標（1始まり）を書き込む
+//
+// 1バイトで表せるのは223まで。UTF-8モード（1005）では2047まで表せる。
+fn encode_position(report: &mut Vec<u8>, position: usize, utf8: bool) -> bool {
+    let value = 32 + position;
+    if utf8 && value >= 128 {
+        match char::from_u32(value as u32).filter(|_| value < 2048) {
+            Some(c) => {
+                let mut buf = [0; 4];
+                report.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                true
+            },
+            None => false,
+        }
+    } else if value <= 255 {
+        report.push(value as u8);
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn report(button: u8, pressed: bool, (line, column): (usize, usize), modifiers: Modifiers, mode: TermMode) -> Option<Vec<u8>> {
+        encode_mouse_report(button, pressed, Point::new(line, Column(column)), modifiers, mode, false)
+    }
+    
+    #[test]
+    fn sgr_press_and_release() {
+        let mode = TermMode::MOUSE_REPORT_CLICK | TermMode::SGR_MOUSE;
+        assert_eq!(report(0, true, (4, 9), Modifiers::NONE, mode).unwrap(), b"\x1b[<0;10;5M");
+        assert_eq!(report(0, false, (4, 9), Modifiers::NONE, mode).unwrap(), b"\x1b[<0;10;5m");
+        // 右ボタン + Ctrl
+        assert_eq!(report(2, true, (0, 0), Modifiers::CTRL, mode).unwrap(), b"\x1b[<18;1;1M");
+        // 座標の上限がない
+        assert_eq!(report(0, true, (0, 299), Modifiers::NONE, mode).unwrap(), b"\x1b[<0;300;1M");
+    }
+    
+    #[test]
+    fn normal_encoding() {
+        let mode = TermMode::MOUSE_REPORT_CLICK;
+        assert_eq!(report(0, true, (0, 0), Modifiers::NONE, mode).unwrap(), b"\x1b[M !!");
+        // 解放時はボタン3として報告する
+        assert_eq!(report(0, false, (1, 2), Modifiers::SHIFT, mode).unwrap(), b"\x1b[M'#\"");
+        // 1バイトで表せない座標は報告しない
+        assert_eq!(report(0, true, (0, 223), Modifiers::NONE, mode), None);
+    }
+    
+    #[test]
+    fn utf8_encoding() {
+        let mode = TermMode::MOUSE_REPORT_CLICK | TermMode::UTF8_MOUSE;
+        assert_eq!(report(0, true, (0, 299), Modifiers::NONE, mode).unwrap(), "\x1b[M \u{14c}!".as_bytes());
+    }
+    
+    #[test]
+    fn urxvt_encoding() {
+        let point = Point::new(4, Column(9));
+        let report = encode_mouse_report(0, false, point, Modifiers::NONE, TermMode::MOUSE_REPORT_CLICK, true);
+        assert_eq!(report.unwrap(), b"\x1b[35;10;5M");
+    }
+    
+    #[test]
+    fn x10_reports_presses_only() {
+        assert_eq!(report(0, true, (0, 0), Modifiers::CTRL, TermMode::NONE).unwrap(), b"\x1b[M !!");
+        assert_eq!(report(0, false, (0, 0), Modifiers::NONE, TermMode::NONE), None);
+    }
+}
@@ -0,0 +1,109 @@
+use egui::{Color32, Modal, RichText, ScrollArea};
+use alacritty_terminal::grid::Scroll;
+use alacritty_terminal::term::TermMode;
+
+use super::TerminalPane;
+
+// 確認ダイアログでプレビューする最大行数
+const PREVIEW_LINES: usize = 20;
+
+impl TerminalPane {
+    // クリップボードのテキストをシェルに貼り付ける
+    //
+    // アプリケーションがブラケットペーストモード（DECSET 2004）を有効にしていない場合、
+    // 複数行や制御文字を含むテキストは確認してから送る。
+    pub(super) fn paste(&mut self, text: &str) {
+        let bracketed = self.term.lock().mode().contains(TermMode::BRACKETED_PASTE);
+        if !bracketed && self.config.confirm_unsafe_paste && is_unsafe_paste(text) {
+            self.pending_paste = Some(text.to_string());
+            return;
+        }
+        
+        self.send_paste(text);
+    }
+    
+    fn send_paste(&mut self, text: &str) {
+        let bracketed = self.term.lock().mode().contains(TermMode::BRACKETED_PASTE);
+        let bytes = if bracketed {
+            // 貼り付けたテキストで括弧が閉じられないようにESCを取り除く
+            format!("\x1b[200~{}\x1b[201~", text.replace('\x1b', "")).into_bytes()
+        } else {
+            // 改行はEnterキーと同じCRとして送る
+            text.replace("\r\n", "\r").replace('\n', "\r").into_bytes()
+        };
+        
+        self.term.lock().scroll_display(Scroll::Bottom);
+        self.write_to_pty(bytes);
+    }
+    
+    // 貼り付けの確認ダイアログ
+    pub(super) fn paste_confirmation_ui(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(text) = &self.pending_paste else {
+            return;
+        };
+        
+        let mut confirmed = false;
+        let mut cancelled = false;
+        
+        let modal = Modal::new(id).show(ctx, |ui| {
+            ui.set_max_width(500.0);
+            ui.heading("貼り付けの確認");
+            ui.label("複数行または制御文字を含むテキストを貼り付けようとしています。\n貼り付けた行はすぐに実行される可能性があります。");
+            ui.add_space(8.0);
+            
+            // 制御文字は見えるようにエスケープして表示する
+            let lines: Vec<&str> = text.lines().collect();
+            let preview = lines
+                .iter()
+                .take(PREVIEW_LINES)
+                .map(|line| escape_control_chars(line))
+                .collect::<Vec<_>>()
+                .join("\n");
+            
+            ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                ui.label(RichText::new(preview).monospace());
+                if lines.len() > PREVIEW_LINES {
+                    ui.label(
+                        RichText::new(format!("…他 {} 行", lines.len() - PREVIEW_LINES))
+                            .color(Color32::GRAY),
+                    );
+                }
+            });
+            
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                confirmed = ui.button("貼り付け").clicked();
+                cancelled = ui.button("キャンセル").clicked();
+            });
+        });
+        
+        if modal.should_close() {
+            cancelled = true;
+        }
+        
+        if confirmed {
+            if let Some(text) = self.pending_paste.take() {
+                self.send_paste(&text);
+            }
+        } else if cancelled {
+            self.pending_paste = None;
+        }
+    }
+}
+
+// 確認なしに貼り付けると危険なテキストかどうか
+fn is_unsafe_paste(text: &str) -> bool {
+    text.chars().any(|c| c == '\n' || c == '\r' || (c.is_control() && c != '\t'))
+}
+
+fn escape_control_chars(line: &str) -> String {
+    line.chars()
+        .map(|c| {
+            if c.is_control() && c != '\t' {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
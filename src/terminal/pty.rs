@@ -0,0 +1,348 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+};
+use alacritty_terminal::event::{OnResize, WindowSize};
+use alacritty_terminal::tty::{self, ChildEvent, EventedPty, EventedReadWrite, Pty};
+use polling::{Event as PollingEvent, PollMode, Poller};
+
+use super::ShellCommand;
+use super::shell_integration::{CommandLog, ShellMarker};
+
+// 読み取るOSCの内容の最大長（OSC 133 の判定に必要な分だけ保持する）
+const MAX_OSC_LEN: usize = 64;
+
+// 開き直すために保持する OSC 8 の内容の最大長（これより長いURIは開き直さない）
+const MAX_HYPERLINK_OSC_LEN: usize = 4096;
+
+// alacritty_terminal が扱わないDECプライベートモードの状態
+//
+// X10マウス（DECSET 9）とurxvt形式のマウス座標（DECSET 1015）は
+// Term では無視されるため、PTYの出力を横取りして追跡する。
+#[derive(Clone, Default)]
+pub(super) struct ExtraModes(Arc<AtomicU8>);
+
+impl ExtraModes {
+    const X10_MOUSE: u8 = 1;
+    const URXVT_MOUSE: u8 = 1 << 1;
+    
+    pub fn x10_mouse(&self) -> bool {
+        self.0.load(Ordering::Relaxed) & Self::X10_MOUSE != 0
+    }
+    
+    pub fn urxvt_mouse(&self) -> bool {
+        self.0.load(Ordering::Relaxed) & Self::URXVT_MOUSE != 0
+    }
+    
+    fn set(&self, flag: u8, enabled: bool) {
+        if enabled {
+            self.0.fetch_or(flag, Ordering::Relaxed);
+        } else {
+            self.0.fetch_and(!flag, Ordering::Relaxed);
+        }
+    }
+    
+    fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+// CSI ? Pm h / CSI ? Pm l と OSC 133、OSC 8 を検出するための最小限のパーサー
+#[derive(Clone, Copy)]
+enum ScanState {
+    Ground,
+    Escape,
+    Csi,
+    PrivateMode,
+    Osc,
+    OscEscape, // OSC の中でESCを読んだ（ST の途中）
+}
+
+struct ModeScanner {
+    state: ScanState,
+    params: Vec<u16>,
+    current: u16,
+    osc: Vec<u8>,
+    osc_truncated: bool,
+    hyperlink: Option<Vec<u8>>, // アプリケーションが開いている OSC 8 の内容
+    modes: ExtraModes,
+    commands: CommandLog,
+}
+
+// シェル統合のマーカーの直後の位置と、その時点でアプリケーションが開いていたハイパーリンク
+type ScannedMarker = (usize, ShellMarker, Option<Vec<u8>>);
+
+impl ModeScanner {
+    fn new(modes: ExtraModes, commands: CommandLog) -> Self {
+        Self {
+            state: ScanState::Ground,
+            params: Vec::new(),
+            current: 0,
+            osc: Vec::new(),
+            osc_truncated: false,
+            hyperlink: None,
+            modes,
+            commands,
+        }
+    }
+    
+    // バイト列を読み進め、シェル統合のマーカーを返す
+    fn advance(&mut self, bytes: &[u8]) -> Vec<ScannedMarker> {
+        let mut markers = Vec::new();
+        for (i, &byte) in bytes.iter().enumerate() {
+            self.state = match (self.state, byte) {
+                // BEL または ST（ESC \）で OSC が終わる
+                (ScanState::Osc, 0x07) | (ScanState::OscEscape, b'\\') => {
+                    if let Some(marker) = ShellMarker::parse(&self.osc) {
+                        markers.push((i + 1, marker, self.hyperlink.clone()));
+                    } else if self.osc.starts_with(b"8;") {
+                        self.track_hyperlink();
+                    }
+                    ScanState::Ground
+                },
+                (ScanState::Osc, 0x1b) => ScanState::OscEscape,
+                (ScanState::Osc, _) => {
+                    let max_len = if self.osc.starts_with(b"8;") { MAX_HYPERLINK_OSC_LEN } else { MAX_OSC_LEN };
+                    if self.osc.len() < max_len {
+                        self.osc.push(byte);
+                    } else {
+                        self.osc_truncated = true;
+                    }
+                    ScanState::Osc
+                },
+                (_, 0x1b) => ScanState::Escape,
+                (ScanState::Escape, b'[') => ScanState::Csi,
+                (ScanState::Escape, b']') => {
+                    self.osc.clear();
+                    self.osc_truncated = false;
+                    ScanState::Osc
+                },
+                // RIS（端末のリセット）で画面とスクロールバックの目印も消える
+                (ScanState::Escape, b'c') => {
+                    self.modes.reset();
+                    self.hyperlink = None;
+                    self.commands.clear();
+                    ScanState::Ground
+                },
+                (ScanState::Csi, b'?') => {
+                    self.params.clear();
+                    self.current = 0;
+                    ScanState::PrivateMode
+                },
+                (ScanState::PrivateMode, b'0'..=b'9') => {
+                    self.current = self.current.saturating_mul(10).saturating_add((byte - b'0') as u16);
+                    ScanState::PrivateMode
+                },
+                (ScanState::PrivateMode, b';') => {
+                    self.params.push(self.current);
+                    self.current = 0;
+                    ScanState::PrivateMode
+                },
+                (ScanState::PrivateMode, b'h' | b'l') => {
+                    self.params.push(self.current);
+                    self.apply(byte == b'h');
+                    ScanState::Ground
+                },
+                _ => ScanState::Ground,
+            };
+        }
+        markers
+    }
+    
+    // OSC 8 ; params ; URI（URIが空なら終了）でアプリケーションのハイパーリンクを追跡する
+    fn track_hyperlink(&mut self) {
+        let uri_present = self.osc.splitn(3, |&b| b == b';').nth(2).is_some_and(|uri| !uri.is_empty());
+        // 途中で切り詰めた内容では開き直せないため、追跡をやめる
+        self.hyperlink = (uri_present && !self.osc_truncated).then(|| self.osc.clone());
+    }
+    
+    fn apply(&mut self, enabled: bool) {
+        for &mode in &self.params {
+            match mode {
+                9 => self.modes.set(ExtraModes::X10_MOUSE, enabled),
+                // 他のマウス報告モードとX10は排他的
+                1000 | 1002 | 1003 if enabled => self.modes.set(ExtraModes::X10_MOUSE, false),
+                1015 => self.modes.set(ExtraModes::URXVT_MOUSE, enabled),
+                _ => {}
+            }
+        }
+    }
+}
+
+// 読み取ったバイト列からモードの変化とシェル統合のマーカーを拾うPTYのラッパー
+//
+// alacritty_terminal は OSC 133 を無視し、このラッパーからは解析時点の
+// カーソル位置も分からない。そこでマーカーの直後に独自のURIを持つ
+// OSC 8 ハイパーリンクを差し込み、プロンプトとコマンドのセルに目印を
+// 付ける。目印はスクロールやリフローでもセルと一緒に移動する。
+//
+// 目印はアプリケーションが開いているハイパーリンクを上書きして閉じるため、
+// 目印を閉じた直後にそのハイパーリンクを開き直す。
+pub(super) struct ModeTrackingPty {
+    pty: Pty,
+    scanner: ModeScanner,
+    pending: Vec<u8>, // 目印を差し込んだために渡しきれなかったバイト列
+}
+
+impl ModeTrackingPty {
+    pub fn new(pty: Pty, modes: ExtraModes, commands: CommandLog) -> Self {
+        Self { pty, scanner: ModeScanner::new(modes, commands), pending: Vec::new() }
+    }
+}
+
+impl Read for ModeTrackingPty {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // 前回渡しきれなかった分を先に渡す
+        if !self.pending.is_empty() {
+            let n = self.pending.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.pending[..n]);
+            self.pending.drain(..n);
+            return Ok(n);
+        }
+        
+        let n = self.pty.reader().read(buf)?;
+        let markers = self.scanner.advance(&buf[..n]);
+        if markers.is_empty() {
+            return Ok(n);
+        }
+        
+        // マーカーの直後に目印のシーケンスを差し込む
+        let mut output = Vec::with_capacity(n + markers.len() * 64);
+        let mut start = 0;
+        for (end, marker, hyperlink) in markers {
+            output.extend_from_slice(&buf[start..end]);
+            output.extend_from_slice(self.scanner.commands.record(marker).as_bytes());
+            if let (ShellMarker::OutputStart | ShellMarker::CommandEnd(_), Some(hyperlink)) = (marker, hyperlink) {
+                output.extend_from_slice(b"\x1b]");
+                output.extend_from_slice(&hyperlink);
+                output.extend_from_slice(b"\x1b\\");
+            }
+            start = end;
+        }
+        output.extend_from_slice(&buf[start..n]);
+        
+        let n = output.len().min(buf.len());
+        buf[..n].copy_from_slice(&output[..n]);
+        self.pending = output.split_off(n);
+        Ok(n)
+    }
+}
+
+impl EventedReadWrite for ModeTrackingPty {
+    type Reader = Self;
+    type Writer = <Pty as EventedReadWrite>::Writer;
+    
+    unsafe fn register(&mut self, poll: &Arc<Poller>, interest: PollingEvent, mode: PollMode) -> io::Result<()> {
+        self.pty.register(poll, interest, mode)
+    }
+    
+    fn reregister(&mut self, poll: &Arc<Poller>, interest: PollingEvent, mode: PollMode) -> io::Result<()> {
+        self.pty.reregister(poll, interest, mode)
+    }
+    
+    fn deregister(&mut self, poll: &Arc<Poller>) -> io::Result<()> {
+        self.pty.deregister(poll)
+    }
+    
+    fn reader(&mut self) -> &mut Self {
+        self
+    }
+    
+    fn writer(&mut self) -> &mut Self::Writer {
+        self.pty.writer()
+    }
+}
+
+impl EventedPty for ModeTrackingPty {
+    fn next_child_event(&mut self) -> Option<ChildEvent> {
+        self.pty.next_child_event()
+    }
+}
+
+impl OnResize for ModeTrackingPty {
+    fn on_resize(&mut self, window_size: WindowSize) {
+        self.pty.on_resize(window_size);
+    }
+}
+
+// シェルを子プロセスとして擬似端末（PTY）上で起動する
+//
+// alacritty_terminal の tty モジュールがスレーブ側を制御端末に設定し、
+// setsid() でシェルを新しいセッションのリーダーにする。
+pub(super) fn spawn_pty(command: &ShellCommand, window_size: WindowSize) -> io::Result<Pty> {
+    // 環境変数を設定して日本語対応
+    let env = HashMap::from([
+        ("LANG".to_string(), "ja_JP.UTF-8".to_string()),
+        ("LC_ALL".to_string(), "ja_JP.UTF-8".to_string()),
+        ("TERM".to_string(), "xterm-256color".to_string()),
+        ("COLORTERM".to_string(), "truecolor".to_string()),
+        // シェル統合のスクリプトがこの端末の中でだけ有効になるようにする
+        ("TERM_PROGRAM".to_string(), "alacritty_chat".to_string()),
+    ]);
+    
+    let options = tty::Options {
+        shell: Some(tty::Shell::new(command.program.clone(), command.args.clone())),
+        working_directory: command.working_directory.clone(),
+        drain_on_exit: false,
+        env,
+    };
+    
+    tty::new(&options, window_size, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn scanner() -> ModeScanner {
+        ModeScanner::new(ExtraModes::default(), CommandLog::default())
+    }
+    
+    #[test]
+    fn private_modes() {
+        let mut scanner = scanner();
+        scanner.advance(b"\x1b[?9h");
+        assert!(scanner.modes.x10_mouse());
+        // 他のマウス報告モードを有効にするとX10は無効になる
+        scanner.advance(b"\x1b[?1015;1000h");
+        assert!(!scanner.modes.x10_mouse());
+        assert!(scanner.modes.urxvt_mouse());
+        // RIS ですべて戻る
+        scanner.advance(b"\x1bc");
+        assert!(!scanner.modes.urxvt_mouse());
+    }
+    
+    #[test]
+    fn shell_markers_with_positions() {
+        let mut scanner = scanner();
+        let bytes = b"\x1b]133;A\x07$ \x1b]133;B\x1b\\ls";
+        let markers = scanner.advance(bytes);
+        assert_eq!(markers, vec![(8, ShellMarker::PromptStart, None), (19, ShellMarker::CommandStart, None)]);
+        assert_eq!(&bytes[19..], b"ls");
+    }
+    
+    #[test]
+    fn shell_marker_split_across_reads() {
+        let mut scanner = scanner();
+        assert!(scanner.advance(b"out\x1b]133;D").is_empty());
+        assert_eq!(scanner.advance(b";127\x07"), vec![(5, ShellMarker::CommandEnd(Some(127)), None)]);
+        // 他のOSCは無視する
+        assert!(scanner.advance(b"\x1b]0;title\x07\x1b]8;;https://example.com\x1b\\").is_empty());
+    }
+    
+    #[test]
+    fn hyperlink_open_at_marker() {
+        let mut scanner = scanner();
+        let markers = scanner.advance(b"\x1b]8;id=1;https://example.com\x1b\\\x1b]133;D;0\x07\x1b]8;;\x07\x1b]133;A\x07");
+        let link = Some(b"8;id=1;https://example.com".to_vec());
+        assert_eq!(markers, vec![(40, ShellMarker::CommandEnd(Some(0)), link), (54, ShellMarker::PromptStart, None)]);
+        
+        // 長すぎて切り詰めたハイパーリンクは開き直さない
+        let long = format!("\x1b]8;;https://example.com/{}\x07\x1b]133;C\x07", "a".repeat(MAX_HYPERLINK_OSC_LEN));
+        assert_eq!(scanner.advance(long.as_bytes())[0].2, None);
+    }
+}
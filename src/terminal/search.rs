@@ -0,0 +1,289 @@
+use egui::{Align2, Color32, Key, RichText, TextEdit, Vec2};
+use std::time::{Duration, Instant};
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::index::{Column, Direction, Point};
+use alacritty_terminal::term::TermMode;
+use alacritty_terminal::term::search::{Match, RegexIter, RegexSearch};
+
+use super::TerminalPane;
+
+// 数える一致の上限（これを超えると「1000+ 件」と表示する）
+const MAX_MATCHES: usize = 1000;
+
+// 出力が続いている間に一致を探し直す間隔
+//
+// 検索はスクロールバック全体を対象に端末をロックしたまま行うため、
+// 出力のたびに探し直すとUIとPTYの読み取りが止まる。
+const RESCAN_INTERVAL: Duration = Duration::from_millis(250);
+
+// 一致した箇所の配色（Alacrittyの既定値と同じ）
+pub(super) const MATCH_FOREGROUND: Color32 = Color32::from_rgb(0x18, 0x18, 0x18);
+pub(super) const MATCH_BACKGROUND: Color32 = Color32::from_rgb(0xac, 0x42, 0x42);
+pub(super) const FOCUSED_MATCH_BACKGROUND: Color32 = Color32::from_rgb(0xf4, 0xbf, 0x75);
+
+// 検索バーの状態
+#[derive(Default)]
+pub(super) struct SearchState {
+    pub open: bool,
+    query: String,
+    use_regex: bool,
+    case_sensitive: bool,
+    regex: Option<RegexSearch>,
+    error: Option<String>,
+    matches: Vec<Match>, // スクロールバックを含む一致（上から順）
+    truncated: bool, // 一致が多すぎて数えきれなかった
+    current: Option<Match>, // 選択中の一致
+    dirty: bool, // 端末の内容か検索条件が変わった
+    updated_at: Option<Instant>, // 最後に一致を探した時刻
+    focus_input: bool,
+}
+
+impl SearchState {
+    // 端末の内容が変わったので一致を探し直す
+    pub(super) fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+    
+    // 検索条件から正規表現を作り直す
+    fn compile(&mut self) {
+        self.regex = None;
+        self.error = None;
+        self.current = None;
+        self.dirty = true;
+        
+        if self.query.is_empty() {
+            return;
+        }
+        
+        let pattern = if self.use_regex { self.query.clone() } else { escape(&self.query) };
+        // RegexSearch は大文字を含むかどうかで大文字小文字の区別を決めるため、フラグで上書きする
+        let flags = if self.case_sensitive { "(?-i)" } else { "(?i)" };
+        match RegexSearch::new(&format!("{}{}", flags, pattern)) {
+            Ok(regex) => self.regex = Some(regex),
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+    
+    // 指定した範囲の行と重なる一致（描画用）
+    pub(super) fn visible_matches(&self, top: i32, bottom: i32) -> Vec<(Match, bool)> {
+        if !self.open {
+            return Vec::new();
+        }
+        
+        self.matches
+            .iter()
+            .filter(|range| range.end().line.0 >= top && range.start().line.0 <= bottom)
+            .map(|range| (range.clone(), self.current.as_ref() == Some(range)))
+            .collect()
+    }
+}
+
+impl TerminalPane {
+    // 検索バーを開き、入力欄にフォーカスを移す
+    pub(super) fn open_search(&mut self) {
+        self.search.open = true;
+        self.search.focus_input = true;
+        self.search.dirty = true;
+        self.search.updated_at = None;
+    }
+    
+    // 検索バーを表示する
+    //
+    // Enterで上（古い出力）へ、Shift+Enterで下へ移動し、Escで閉じる。
+    // 閉じた場合は true を返す。
+    pub(super) fn search_ui(&mut self, ui: &egui::Ui, rect: egui::Rect, id: egui::Id) -> bool {
+        if !self.search.open {
+            return false;
+        }
+        
+        // 出力による変化は一定の間隔でまとめて反映する
+        if self.search.dirty {
+            match self.search.updated_at.map(|time| time.elapsed()) {
+                Some(elapsed) if elapsed < RESCAN_INTERVAL => {
+                    ui.ctx().request_repaint_after(RESCAN_INTERVAL - elapsed);
+                },
+                _ => self.update_search_matches(),
+            }
+        }
+        
+        let mut changed = false;
+        let mut step = None;
+        let mut close = false;
+        
+        egui::Area::new(id)
+            .order(egui::Order::Foreground)
+            .pivot(Align2::RIGHT_TOP)
+            .fixed_pos(rect.right_top() + Vec2::new(-8.0, 8.0))
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let input = ui.add(
+                            TextEdit::singleline(&mut self.search.query)
+                                .desired_width(200.0)
+                                .hint_text("検索"),
+                        );
+                        if self.search.focus_input {
+                            input.request_focus();
+                            self.search.focus_input = false;
+                        }
+                        changed |= input.changed();
+                        
+                        // Enterで確定するとフォーカスが外れるため戻す
+                        if input.lost_focus() {
+                            let (enter, shift, escape) = ui.input(|i| {
+                                (i.key_pressed(Key::Enter), i.modifiers.shift, i.key_pressed(Key::Escape))
+                            });
+                            if enter {
+                                step = Some(shift);
+                                input.request_focus();
+                            } else if escape {
+                                close = true;
+                            }
+                        }
+                        
+                        changed |= ui.toggle_value(&mut self.search.use_regex, ".*").on_hover_text("正規表現").changed();
+                        changed |= ui
+                            .toggle_value(&mut self.search.case_sensitive, "Aa")
+                            .on_hover_text("大文字と小文字を区別する")
+                            .changed();
+                        
+                        if ui.button("▲").on_hover_text("前を検索 (Enter)").clicked() {
+                            step = Some(false);
+                        }
+                        if ui.button("▼").on_hover_text("次を検索 (Shift+Enter)").clicked() {
+                            step = Some(true);
+                        }
+                        
+                        ui.label(self.search_status());
+                        
+                        if ui.button("✕").clicked() {
+                            close = true;
+                        }
+                    });
+                    
+                    if let Some(error) = &self.search.error {
+                        ui.label(RichText::new(error).color(Color32::from_rgb(255, 100, 100)).small());
+                    }
+                });
+            });
+        
+        // 一致の表示を次のフレームに反映する
+        if changed || step.is_some() || close {
+            ui.ctx().request_repaint();
+        }
+        
+        if changed {
+            self.search.compile();
+            self.update_search_matches();
+            // 入力中は最も新しい一致へ移動する
+            self.step_search_match(false);
+        }
+        if let Some(forward) = step {
+            self.step_search_match(forward);
+        }
+        if close {
+            self.search.open = false;
+            self.search.current = None;
+            self.search.matches.clear();
+        }
+        close
+    }
+    
+    // 一致件数の表示
+    fn search_status(&self) -> String {
+        let search = &self.search;
+        if search.regex.is_none() {
+            return String::new();
+        }
+        
+        let count = if search.truncated {
+            format!("{}+", MAX_MATCHES)
+        } else {
+            search.matches.len().to_string()
+        };
+        let index = search
+            .current
+            .as_ref()
+            .and_then(|current| search.matches.iter().position(|range| range == current));
+        match index {
+            Some(index) => format!("{} / {}", index + 1, count),
+            None => format!("{} 件", count),
+        }
+    }
+    
+    // スクロールバックを含むグリッド全体から一致を探す
+    fn update_search_matches(&mut self) {
+        self.search.dirty = false;
+        self.search.updated_at = Some(Instant::now());
+        self.search.matches.clear();
+        self.search.truncated = false;
+        
+        let Some(regex) = &mut self.search.regex else {
+            return;
+        };
+        
+        let term = self.term.lock();
+        let start = Point::new(term.topmost_line(), Column(0));
+        let end = Point::new(term.bottommost_line(), term.last_column());
+        // 数えきれない場合に新しい一致を優先するため、下から探す
+        let mut matches: Vec<Match> = RegexIter::new(end, start, Direction::Left, &term, regex)
+            .take(MAX_MATCHES + 1)
+            .collect();
+        
+        self.search.truncated = matches.len() > MAX_MATCHES;
+        matches.truncate(MAX_MATCHES);
+        matches.reverse();
+        
+        // 出力で行が移動して選択中の一致がなくなった場合は選択を外す
+        if let Some(current) = &self.search.current {
+            if !matches.contains(current) {
+                self.search.current = None;
+            }
+        }
+        self.search.matches = matches;
+    }
+    
+    // 次（forward）または前の一致を選択し、見える位置までスクロールする
+    pub(super) fn step_search_match(&mut self, forward: bool) {
+        let matches = &self.search.matches;
+        if matches.is_empty() {
+            self.search.current = None;
+            return;
+        }
+        
+        let index = match &self.search.current {
+            Some(current) => {
+                let index = matches.iter().position(|range| range == current).unwrap_or(0);
+                if forward {
+                    (index + 1) % matches.len()
+                } else {
+                    (index + matches.len() - 1) % matches.len()
+                }
+            },
+            // 最初は最も新しい（最後の）一致から始める
+            None => matches.len() - 1,
+        };
+        
+        let range = matches[index].clone();
+        let mut term = self.term.lock();
+        term.scroll_to_point(*range.start());
+        // viモード中はviカーソルも一致の先頭に移動する
+        if term.mode().contains(TermMode::VI) {
+            term.vi_goto_point(*range.start());
+        }
+        drop(term);
+        self.search.current = Some(range);
+    }
+}
+
+// 正規表現の特殊文字をエスケープする
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,513 @@
+use egui::{Color32, Rect, Pos2, Vec2};
+use std::{
+    collections::VecDeque,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use alacritty_terminal::grid::{Dimensions, Scroll};
+use alacritty_terminal::index::{Column, Line, Point, Side};
+use alacritty_terminal::selection::{Selection, SelectionType};
+use alacritty_terminal::term::Term;
+use alacritty_terminal::term::cell::{Flags, Hyperlink};
+
+use super::TerminalPane;
+
+// プロンプトとコマンドのセルに付ける目印のURIの接頭辞
+const MARK_SCHEME: &str = "alacritty-chat-shell:";
+
+// 目印のハイパーリンクを終えるシーケンス（OSC 8 ;; ST）
+const END_MARK: &str = "\x1b]8;;\x1b\\";
+
+// 左端の余白の幅と、その中に描くマークの幅（ピクセル）
+pub(super) const GUTTER_WIDTH: f32 = 6.0;
+const GUTTER_MARK_WIDTH: f32 = 3.0;
+
+// 記録がこの数だけ増えるごとに、グリッドから消えたコマンドの記録を捨てる
+const PRUNE_INTERVAL: usize = 64;
+
+// マークの色
+const SUCCESS_COLOR: Color32 = Color32::from_rgb(80, 160, 90);
+const FAILURE_COLOR: Color32 = Color32::from_rgb(220, 70, 70);
+const PENDING_COLOR: Color32 = Color32::from_rgb(120, 120, 120);
+
+// FinalTerm 形式（OSC 133）のシェル統合のマーカー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ShellMarker {
+    PromptStart,             // A: プロンプトの表示開始
+    CommandStart,            // B: コマンドの入力開始
+    OutputStart,             // C: コマンドの実行開始
+    CommandEnd(Option<i32>), // D: コマンドの終了と終了コード
+}
+
+impl ShellMarker {
+    // OSC の内容（ESC ] と終端を除いた部分）を解析する
+    pub fn parse(osc: &[u8]) -> Option<Self> {
+        let osc = std::str::from_utf8(osc).ok()?;
+        let mut params = osc.split(';');
+        if params.next()? != "133" {
+            return None;
+        }
+        
+        match params.next()? {
+            "A" => Some(Self::PromptStart),
+            "B" => Some(Self::CommandStart),
+            "C" => Some(Self::OutputStart),
+            "D" => Some(Self::CommandEnd(params.next().and_then(|code| code.parse().ok()))),
+            _ => None,
+        }
+    }
+}
+
+// 目印の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MarkKind {
+    Prompt,
+    Command,
+}
+
+// 目印のハイパーリンクから種類とコマンドの番号を取り出す
+fn parse_mark(hyperlink: &Hyperlink) -> Option<(MarkKind, usize)> {
+    let mark = hyperlink.uri().strip_prefix(MARK_SCHEME)?;
+    let (kind, id) = mark.split_once('/')?;
+    let kind = match kind {
+        "prompt" => MarkKind::Prompt,
+        "command" => MarkKind::Command,
+        _ => return None,
+    };
+    Some((kind, id.parse().ok()?))
+}
+
+// シェル統合の目印として差し込んだハイパーリンクかどうか
+pub(super) fn is_mark(hyperlink: &Hyperlink) -> bool {
+    hyperlink.uri().starts_with(MARK_SCHEME)
+}
+
+// コマンドの実行状態
+#[derive(Debug, Clone, Copy)]
+pub(super) enum CommandState {
+    // プロンプトを表示したがまだ実行していない
+    Pending,
+    Running { started: Instant },
+    Finished { exit_code: Option<i32>, duration: Duration },
+}
+
+#[derive(Default)]
+struct CommandStatus {
+    started: Option<Instant>,
+    finished: Option<(Instant, Option<i32>)>,
+}
+
+// 記録しているコマンド
+//
+// 番号は捨てた記録の分も含めて増え続けるため、グリッドに残っている古い目印と
+// 新しいコマンドの番号が重なることはない。
+#[derive(Default)]
+struct Commands {
+    first_id: usize, // statuses の先頭の番号
+    statuses: VecDeque<CommandStatus>,
+    next_prune: usize, // 次に古い記録を捨てる記録の数
+}
+
+impl Commands {
+    fn push(&mut self) -> usize {
+        self.statuses.push_back(CommandStatus::default());
+        self.first_id + self.statuses.len() - 1
+    }
+    
+    fn last_id(&self) -> Option<usize> {
+        (!self.statuses.is_empty()).then(|| self.first_id + self.statuses.len() - 1)
+    }
+    
+    // 指定した番号より古い記録を捨てる
+    fn drop_before(&mut self, id: usize) {
+        let count = id.saturating_sub(self.first_id).min(self.statuses.len());
+        self.statuses.drain(..count);
+        self.first_id += count;
+    }
+}
+
+// PTYの読み取りスレッドが記録するコマンドの実行状態（番号はプロンプトの表示順）
+#[derive(Clone, Default)]
+pub(super) struct CommandLog(Arc<Mutex<Commands>>);
+
+impl CommandLog {
+    // マーカーを記録し、出力に差し込む目印のシーケンスを返す
+    pub fn record(&self, marker: ShellMarker) -> String {
+        let mut commands = self.0.lock().unwrap();
+        match marker {
+            ShellMarker::PromptStart => {
+                let id = commands.push();
+                format!("\x1b]8;;{}prompt/{}\x1b\\", MARK_SCHEME, id)
+            },
+            ShellMarker::CommandStart => {
+                // プロンプトの開始を出さないシェルでもコマンドを記録できるようにする
+                let id = match commands.last_id() {
+                    Some(id) if commands.statuses.back().is_some_and(|status| status.started.is_none()) => id,
+                    _ => commands.push(),
+                };
+                format!("\x1b]8;;{}command/{}\x1b\\", MARK_SCHEME, id)
+            },
+            ShellMarker::OutputStart => {
+                if let Some(status) = commands.statuses.back_mut() {
+                    status.started.get_or_insert_with(Instant::now);
+                }
+                END_MARK.to_string()
+            },
+            ShellMarker::CommandEnd(exit_code) => {
+                // 実行せずに終えたプロンプト（Ctrl+C など）の終了コードは記録しない
+                if let Some(status) = commands.statuses.back_mut() {
+                    if status.started.is_some() && status.finished.is_none() {
+                        status.finished = Some((Instant::now(), exit_code));
+                    }
+                }
+                END_MARK.to_string()
+            },
+        }
+    }
+    
+    // マーカーを一度も受け取っていない（シェル統合が有効でない）かどうか
+    pub fn is_empty(&self) -> bool {
+        let commands = self.0.lock().unwrap();
+        commands.first_id == 0 && commands.statuses.is_empty()
+    }
+    
+    // 端末のリセットで画面とスクロールバックが消えたときにすべての記録を捨てる
+    pub fn clear(&self) {
+        let mut commands = self.0.lock().unwrap();
+        let next_id = commands.first_id + commands.statuses.len();
+        commands.drop_before(next_id);
+        commands.next_prune = 0;
+    }
+    
+    // 前回から記録が増え、古い記録を捨てる時期かどうか
+    fn needs_prune(&self) -> bool {
+        let commands = self.0.lock().unwrap();
+        commands.statuses.len() >= commands.next_prune + PRUNE_INTERVAL
+    }
+    
+    // グリッドに残っている最も古い目印より前の記録を捨てる
+    //
+    // 目印がひとつも見つからない場合は、記録したばかりでまだ端末が
+    // 処理していないコマンドを捨てないよう何もしない。
+    fn prune(&self, oldest_id: Option<usize>) {
+        let mut commands = self.0.lock().unwrap();
+        if let Some(id) = oldest_id {
+            commands.drop_before(id);
+        }
+        commands.next_prune = commands.statuses.len();
+    }
+    
+    fn state(&self, id: usize) -> CommandState {
+        let commands = self.0.lock().unwrap();
+        let status = id.checked_sub(commands.first_id).and_then(|index| commands.statuses.get(index));
+        match status {
+            Some(CommandStatus { started: Some(started), finished: Some((finished, exit_code)) }) => {
+                CommandState::Finished { exit_code: *exit_code, duration: finished.duration_since(*started) }
+            },
+            Some(CommandStatus { started: Some(started), finished: None }) => {
+                CommandState::Running { started: *started }
+            },
+            _ => CommandState::Pending,
+        }
+    }
+}
+
+// 画面上のプロンプトとコマンド、その出力のまとまり
+pub(super) struct CommandBlock {
+    pub id: usize,
+    pub prompt: Line, // プロンプトの最初の行
+    pub command: String,
+    pub output: Option<RangeInclusive<Line>>, // 出力の行（実行していない場合や出力がない場合はNone）
+    pub state: CommandState,
+}
+
+// 指定した範囲の行から目印を探し、コマンドの一覧を作る
+//
+// 出力は入力の次の行から次のプロンプトの手前まで、最後のコマンドでは
+// カーソルのある行（カーソルが行頭ならその前の行）までとする。
+pub(super) fn command_blocks<T>(term: &Term<T>, log: &CommandLog, lines: RangeInclusive<Line>) -> Vec<CommandBlock> {
+    let columns = term.columns();
+    let mut blocks: Vec<CommandBlock> = Vec::new();
+    let mut input_ends: Vec<Line> = Vec::new(); // 目印のある最後の行
+    
+    for line in lines.start().0..=lines.end().0 {
+        let line = Line(line);
+        let row = &term.grid()[line];
+        for column in 0..columns {
+            let cell = &row[Column(column)];
+            let Some((kind, id)) = cell.hyperlink().as_ref().and_then(parse_mark) else {
+                continue;
+            };
+            
+            let index = match blocks.iter().rposition(|block| block.id == id) {
+                Some(index) => index,
+                None => {
+                    blocks.push(CommandBlock {
+                        id,
+                        prompt: line,
+                        command: String::new(),
+                        output: None,
+                        state: log.state(id),
+                    });
+                    input_ends.push(line);
+                    blocks.len() - 1
+                },
+            };
+            
+            if kind == MarkKind::Command && !cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+                // 折り返しではない改行は改行として残す
+                let last = input_ends[index];
+                let block = &mut blocks[index];
+                if last < line && !block.command.is_empty() && !term.grid()[last][Column(columns - 1)].flags.contains(Flags::WRAPLINE) {
+                    block.command.push('\n');
+                }
+                block.command.push(cell.c);
+            }
+            input_ends[index] = line;
+        }
+    }
+    
+    // 最後のコマンドの出力はカーソルの手前まで
+    let cursor = term.grid().cursor.point;
+    let last_output_line = if cursor.column.0 > 0 { cursor.line } else { Line(cursor.line.0 - 1) };
+    let last_output_line = last_output_line.min(*lines.end());
+    
+    let next_prompts: Vec<Line> = blocks.iter().skip(1).map(|block| Line(block.prompt.0 - 1)).collect();
+    for (index, block) in blocks.iter_mut().enumerate() {
+        block.command = block.command.trim().to_string();
+        if matches!(block.state, CommandState::Pending) {
+            continue;
+        }
+        
+        let start = Line(input_ends[index].0 + 1);
+        let end = next_prompts.get(index).copied().unwrap_or(last_output_line);
+        if start <= end {
+            block.output = Some(start..=end);
+        }
+    }
+    blocks
+}
+
+impl TerminalPane {
+    // スクロールバックを含むすべてのコマンド
+    pub(super) fn command_blocks(&self) -> Vec<CommandBlock> {
+        let term = self.term.lock();
+        command_blocks(&*term, &self.commands, term.topmost_line()..=term.bottommost_line())
+    }
+    
+    // スクロールバックから消えたコマンドの記録を捨てる
+    //
+    // 目印は番号の順に並ぶため、グリッドの先頭から最初に見つかった目印より
+    // 古い番号はもう参照されない。
+    pub(super) fn prune_commands(&self) {
+        if !self.commands.needs_prune() {
+            return;
+        }
+        
+        let term = self.term.lock();
+        let columns = term.columns();
+        let oldest_id = (term.topmost_line().0..=term.bottommost_line().0).find_map(|line| {
+            let row = &term.grid()[Line(line)];
+            (0..columns).find_map(|column| {
+                row[Column(column)].hyperlink().as_ref().and_then(parse_mark).map(|(_, id)| id)
+            })
+        });
+        self.commands.prune(oldest_id);
+    }
+    
+    // 前後のプロンプトが表示の先頭に来るようにスクロールする
+    pub(super) fn jump_to_prompt(&mut self, forward: bool) {
+        let blocks = self.command_blocks();
+        let mut term = self.term.lock();
+        let top = Line(-(term.grid().display_offset() as i32));
+        let target = if forward {
+            blocks.iter().map(|block| block.prompt).find(|&prompt| prompt > top)
+        } else {
+            blocks.iter().map(|block| block.prompt).rfind(|&prompt| prompt < top)
+        };
+        
+        if let Some(prompt) = target {
+            // 最下部より下にはスクロールできないため、表示位置は0で止まる
+            let offset = (-prompt.0).max(0);
+            let current = term.grid().display_offset() as i32;
+            term.scroll_display(Scroll::Delta(offset - current));
+        }
+    }
+    
+    // 最後に実行したコマンドの出力を選択する
+    pub(super) fn select_last_output(&mut self) {
+        let blocks = self.command_blocks();
+        if let Some(output) = blocks.iter().rev().find_map(|block| block.output.clone()) {
+            self.select_lines(output);
+        }
+    }
+    
+    // 行単位で選択し、選択の先頭が見えるようにスクロールする
+    fn select_lines(&mut self, lines: RangeInclusive<Line>) {
+        let mut term = self.term.lock();
+        let last_column = term.last_column();
+        let mut selection = Selection::new(SelectionType::Lines, Point::new(*lines.start(), Column(0)), Side::Left);
+        selection.update(Point::new(*lines.end(), last_column), Side::Right);
+        term.selection = Some(selection);
+        
+        let top = -(term.grid().display_offset() as i32);
+        let bottom = top + term.screen_lines() as i32 - 1;
+        if lines.start().0 < top || lines.start().0 > bottom {
+            let offset = (-lines.start().0).max(0);
+            let current = term.grid().display_offset() as i32;
+            term.scroll_display(Scroll::Delta(offset - current));
+        }
+    }
+    
+    // 左端の余白にプロンプトの位置と終了状態を表示する
+    //
+    // マークにポインタを重ねるとコマンドの詳細を表示し、クリックでその出力を選択する。
+    pub(super) fn gutter_ui(&mut self, ui: &egui::Ui, id: egui::Id, rect: Rect) {
+        let response = ui.interact(rect, id, egui::Sense::click());
+        
+        let term = self.term.lock();
+        let display_offset = term.grid().display_offset() as i32;
+        let top = Line(-display_offset);
+        let bottom = Line(term.screen_lines() as i32 - 1 - display_offset);
+        // 表示の先頭より上から始まるプロンプトにはマークを付けない
+        let from = Line((top.0 - 1).max(term.topmost_line().0));
+        let blocks = command_blocks(&*term, &self.commands, from..=bottom);
+        drop(term);
+        
+        let pointer = response.hover_pos();
+        let mut hovered = None;
+        for block in blocks.iter().filter(|block| block.prompt >= top) {
+            let row = block.prompt.0 + display_offset;
+            let mark = Rect::from_min_size(
+                Pos2::new(rect.left() + 1.0, rect.top() + row as f32 * self.cell_size.1),
+                Vec2::new(GUTTER_MARK_WIDTH, self.cell_size.1),
+            );
+            let color = match block.state {
+                CommandState::Pending | CommandState::Running { .. } => PENDING_COLOR,
+                CommandState::Finished { exit_code: Some(code), .. } if code != 0 => FAILURE_COLOR,
+                CommandState::Finished { .. } => SUCCESS_COLOR,
+            };
+            ui.painter().rect_filled(mark, 1.0, color);
+            
+            if pointer.is_some_and(|pos| pos.y >= mark.top() && pos.y < mark.bottom()) {
+                hovered = Some(block);
+            }
+        }
+        
+        let Some(block) = hovered else {
+            return;
+        };
+        
+        if response.clicked() {
+            // 画面外に続く出力も含めるため、スクロールバック全体から探し直す
+            let id = block.id;
+            if let Some(output) = self.command_blocks().into_iter().find(|block| block.id == id).and_then(|block| block.output) {
+                self.select_lines(output);
+            }
+        } else {
+            let command = if block.command.is_empty() { "（コマンドなし）" } else { &block.command };
+            let status = match block.state {
+                CommandState::Pending => "未実行".to_string(),
+                CommandState::Running { started } => format!("実行中（{}）", format_duration(started.elapsed())),
+                CommandState::Finished { exit_code, duration } => {
+                    let code = exit_code.map_or("不明".to_string(), |code| code.to_string());
+                    format!("終了コード: {}\n実行時間: {}", code, format_duration(duration))
+                },
+            };
+            response.on_hover_ui_at_pointer(|ui| {
+                ui.monospace(command);
+                ui.label(status);
+            });
+        }
+    }
+}
+
+// 実行時間を読みやすい形式にする
+fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis < 1000 {
+        format!("{}ms", millis)
+    } else if millis < 60_000 {
+        format!("{:.1}秒", duration.as_secs_f32())
+    } else {
+        let secs = duration.as_secs();
+        format!("{}分{}秒", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parse_markers() {
+        assert_eq!(ShellMarker::parse(b"133;A"), Some(ShellMarker::PromptStart));
+        assert_eq!(ShellMarker::parse(b"133;B"), Some(ShellMarker::CommandStart));
+        assert_eq!(ShellMarker::parse(b"133;C"), Some(ShellMarker::OutputStart));
+        assert_eq!(ShellMarker::parse(b"133;D;1"), Some(ShellMarker::CommandEnd(Some(1))));
+        assert_eq!(ShellMarker::parse(b"133;D"), Some(ShellMarker::CommandEnd(None)));
+        // 追加のパラメータは無視する
+        assert_eq!(ShellMarker::parse(b"133;A;cl=m;aid=1"), Some(ShellMarker::PromptStart));
+        assert_eq!(ShellMarker::parse(b"133;Z"), None);
+        assert_eq!(ShellMarker::parse(b"8;;https://example.com"), None);
+        assert_eq!(ShellMarker::parse(b"1330;A"), None);
+    }
+    
+    #[test]
+    fn record_command_states() {
+        let log = CommandLog::default();
+        assert!(log.is_empty());
+        
+        assert_eq!(log.record(ShellMarker::PromptStart), "\x1b]8;;alacritty-chat-shell:prompt/0\x1b\\");
+        assert_eq!(log.record(ShellMarker::CommandStart), "\x1b]8;;alacritty-chat-shell:command/0\x1b\\");
+        assert!(matches!(log.state(0), CommandState::Pending));
+        
+        assert_eq!(log.record(ShellMarker::OutputStart), END_MARK);
+        assert!(matches!(log.state(0), CommandState::Running { .. }));
+        log.record(ShellMarker::CommandEnd(Some(2)));
+        assert!(matches!(log.state(0), CommandState::Finished { exit_code: Some(2), .. }));
+        
+        // 実行しなかったプロンプトの終了コードは記録しない
+        log.record(ShellMarker::PromptStart);
+        log.record(ShellMarker::CommandStart);
+        log.record(ShellMarker::CommandEnd(Some(130)));
+        assert!(matches!(log.state(1), CommandState::Pending));
+    }
+    
+    #[test]
+    fn prune_and_clear_command_log() {
+        let log = CommandLog::default();
+        for _ in 0..PRUNE_INTERVAL {
+            log.record(ShellMarker::PromptStart);
+            log.record(ShellMarker::OutputStart);
+        }
+        assert!(log.needs_prune());
+        
+        // グリッドに残っている最も古い目印より前を捨てる
+        log.prune(Some(10));
+        assert!(!log.needs_prune());
+        assert!(matches!(log.state(9), CommandState::Pending));
+        assert!(matches!(log.state(10), CommandState::Running { .. }));
+        
+        // 目印が見つからない場合は何も捨てない
+        log.prune(None);
+        assert!(matches!(log.state(10), CommandState::Running { .. }));
+        
+        // リセット後も番号は続きから振る
+        log.clear();
+        assert!(!log.is_empty());
+        assert!(matches!(log.state(PRUNE_INTERVAL - 1), CommandState::Pending));
+        let next = format!("\x1b]8;;alacritty-chat-shell:prompt/{}\x1b\\", PRUNE_INTERVAL);
+        assert_eq!(log.record(ShellMarker::PromptStart), next);
+    }
+    
+    #[test]
+    fn parse_mark_uri() {
+        let mark = |uri: &str| parse_mark(&Hyperlink::new(None::<String>, uri.to_string()));
+        assert_eq!(mark("alacritty-chat-shell:prompt/3"), Some((MarkKind::Prompt, 3)));
+        assert_eq!(mark("alacritty-chat-shell:command/12"), Some((MarkKind::Command, 12)));
+        assert_eq!(mark("alacritty-chat-shell:other/1"), None);
+        assert_eq!(mark("https://example.com"), None);
+    }
+}
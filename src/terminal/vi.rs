@@ -0,0 +1,252 @@
+use egui::{Event, Key};
+use alacritty_terminal::grid::{Dimensions, Scroll};
+use alacritty_terminal::index::{Boundary, Column, Point, Side};
+use alacritty_terminal::selection::{Selection, SelectionType};
+use alacritty_terminal::term::TermMode;
+use alacritty_terminal::vi_mode::ViMotion;
+
+use super::TerminalPane;
+
+// 続く入力を待っているviコマンド
+#[derive(Debug, Clone, Copy)]
+enum Pending {
+    // gg
+    Goto,
+    // f/F/t/T（forward: 右向き、before: 文字の手前で止まる）
+    Find { forward: bool, before: bool },
+}
+
+// viモードの状態
+#[derive(Default)]
+pub(super) struct ViState {
+    pending: Option<Pending>,
+    search_forward: bool, // 最後の検索が `/`（下向き）かどうか
+    history_size: usize, // 前回確認したスクロールバックの行数
+}
+
+impl TerminalPane {
+    // viモードを切り替える
+    //
+    // viモード中は出力が届いても表示位置を保ち、キー入力はシェルに送らない。
+    pub(super) fn toggle_vi_mode(&mut self) {
+        let mut term = self.term.lock();
+        term.toggle_vi_mode();
+        term.selection = None;
+        if !term.mode().contains(TermMode::VI) {
+            term.scroll_display(Scroll::Bottom);
+        }
+        self.vi.history_size = term.history_size();
+        self.vi.pending = None;
+    }
+    
+    // viモード中は新しい出力で表示が流れないようにする
+    //
+    // 最下部を表示しているときは出力に合わせて表示も移動するため、
+    // 増えたスクロールバックの行数だけ表示を戻す。スクロールバックが
+    // 上限に達した後は行数が増えないため、古い行から流れていく。
+    pub(super) fn hold_vi_view(&mut self) {
+        let mut term = self.term.lock();
+        if !term.mode().contains(TermMode::VI) {
+            return;
+        }
+        
+        let history_size = term.history_size();
+        let grown = history_size.saturating_sub(self.vi.history_size);
+        if grown > 0 && term.grid().display_offset() == 0 {
+            term.scroll_display(Scroll::Delta(grown as i32));
+        }
+        self.vi.history_size = history_size;
+    }
+    
+    // viモードのキー入力を処理する
+    pub(super) fn handle_vi_input(&mut self, ui: &egui::Ui) {
+        let events = ui.input(|i| i.events.clone());
+        for event in events {
+            match event {
+                Event::Copy => self.copy_selection(ui.ctx()),
+                // Ctrl+V はeguiがPasteイベントに変換する。クリップボードが空だと
+                // イベント自体が届かないため、vimと同じく Ctrl+Q でも矩形選択できる
+                Event::Paste(_) => self.toggle_vi_selection(SelectionType::Block),
+                Event::Key { key, pressed: true, modifiers, .. } if modifiers.ctrl => self.vi_control_key(key),
+                Event::Key { key, pressed: true, .. } => self.vi_key(key),
+                Event::Text(text) => {
+                    for c in text.chars() {
+                        self.vi_char(ui.ctx(), c);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+    
+    // 文字を入力しないキー（Escや矢印キー）
+    fn vi_key(&mut self, key: Key) {
+        let motion = match key {
+            Key::ArrowUp => ViMotion::Up,
+            Key::ArrowDown => ViMotion::Down,
+            Key::ArrowLeft => ViMotion::Left,
+            Key::ArrowRight => ViMotion::Right,
+            Key::Home => ViMotion::First,
+            Key::End => ViMotion::Last,
+            Key::Escape => {
+                // 選択中なら選択を解除し、そうでなければviモードを抜ける
+                self.vi.pending = None;
+                let mut term = self.term.lock();
+                if term.selection.take().is_none() {
+                    drop(term);
+                    self.toggle_vi_mode();
+                }
+                return;
+            },
+            _ => return,
+        };
+        self.term.lock().vi_motion(motion);
+    }
+    
+    // Ctrl+キーによるスクロールと矩形選択
+    fn vi_control_key(&mut self, key: Key) {
+        let screen_lines = self.term.lock().screen_lines() as i32;
+        match key {
+            Key::Q => self.toggle_vi_selection(SelectionType::Block),
+            Key::U => self.vi_scroll(screen_lines / 2),
+            Key::D => self.vi_scroll(-(screen_lines / 2)),
+            Key::B => self.vi_scroll(screen_lines),
+            Key::F => self.vi_scroll(-screen_lines),
+            Key::Y => self.vi_scroll(1),
+            Key::E => self.vi_scroll(-1),
+            _ => {},
+        }
+    }
+    
+    // viコマンドの文字
+    fn vi_char(&mut self, ctx: &egui::Context, c: char) {
+        match self.vi.pending.take() {
+            Some(Pending::Goto) => {
+                if c == 'g' {
+                    let mut term = self.term.lock();
+                    let point = Point::new(term.topmost_line(), Column(0));
+                    term.vi_goto_point(point);
+                }
+                return;
+            },
+            Some(Pending::Find { forward, before }) => {
+                self.vi_find(c, forward, before);
+                return;
+            },
+            None => {},
+        }
+        
+        let motion = match c {
+            'h' => ViMotion::Left,
+            'j' => ViMotion::Down,
+            'k' => ViMotion::Up,
+            'l' => ViMotion::Right,
+            'w' => ViMotion::SemanticRight,
+            'b' => ViMotion::SemanticLeft,
+            'e' => ViMotion::SemanticRightEnd,
+            'W' => ViMotion::WordRight,
+            'B' => ViMotion::WordLeft,
+            'E' => ViMotion::WordRightEnd,
+            '0' => ViMotion::First,
+            '$' => ViMotion::Last,
+            '^' => ViMotion::FirstOccupied,
+            'H' => ViMotion::High,
+            'M' => ViMotion::Middle,
+            'L' => ViMotion::Low,
+            '%' => ViMotion::Bracket,
+            'G' => {
+                let mut term = self.term.lock();
+                let point = Point::new(term.bottommost_line(), Column(0));
+                term.vi_goto_point(point);
+                return;
+            },
+            'g' => {
+                self.vi.pending = Some(Pending::Goto);
+                return;
+            },
+            'f' | 'F' | 't' | 'T' => {
+                let forward = c.is_lowercase();
+                let before = c.eq_ignore_ascii_case(&'t');
+                self.vi.pending = Some(Pending::Find { forward, before });
+                return;
+            },
+            'v' => {
+                self.toggle_vi_selection(SelectionType::Simple);
+                return;
+            },
+            'V' => {
+                self.toggle_vi_selection(SelectionType::Lines);
+                return;
+            },
+            'y' => {
+                // 選択範囲をクリップボードにコピーして選択を解除する
+                self.copy_selection(ctx);
+                self.term.lock().selection = None;
+                return;
+            },
+            '/' | '?' => {
+                self.vi.search_forward = c == '/';
+                self.open_search();
+                return;
+            },
+            'n' | 'N' => {
+                let forward = (c == 'n') == self.vi.search_forward;
+                self.step_search_match(forward);
+                return;
+            },
+            'i' => {
+                self.toggle_vi_mode();
+                return;
+            },
+            _ => return,
+        };
+        self.term.lock().vi_motion(motion);
+    }
+    
+    // 同じ行で文字を探して移動する（f/F/t/T）
+    fn vi_find(&mut self, c: char, forward: bool, before: bool) {
+        let mut term = self.term.lock();
+        let origin = term.vi_mode_cursor.point;
+        let needle = c.to_string();
+        
+        let found = if forward {
+            term.inline_search_right(origin, &needle)
+        } else {
+            term.inline_search_left(origin, &needle)
+        };
+        let Ok(mut point) = found else {
+            return;
+        };
+        
+        if before {
+            point = if forward {
+                point.sub(&*term, Boundary::None, 1)
+            } else {
+                point.add(&*term, Boundary::None, 1)
+            };
+        }
+        term.vi_goto_point(point);
+    }
+    
+    // 表示をスクロールし、viカーソルも同じ行数だけ動かす
+    fn vi_scroll(&mut self, lines: i32) {
+        let mut term = self.term.lock();
+        let point = term.vi_mode_cursor.scroll(&term, lines).point;
+        term.scroll_display(Scroll::Delta(lines));
+        term.vi_goto_point(point);
+    }
+    
+    // ビジュアル選択を開始する。同じ種類の選択中なら解除する
+    fn toggle_vi_selection(&mut self, ty: SelectionType) {
+        let mut term = self.term.lock();
+        if term.selection.as_ref().is_some_and(|selection| selection.ty == ty) {
+            term.selection = None;
+            return;
+        }
+        
+        let point = term.vi_mode_cursor.point;
+        let mut selection = Selection::new(ty, point, Side::Left);
+        selection.include_all();
+        term.selection = Some(selection);
+    }
+}